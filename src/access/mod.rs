@@ -0,0 +1,116 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use poise::serenity_prelude::{CreateEmbed, Color, Timestamp};
+use poise::CreateReply;
+
+use crate::permissions::{self, Capability};
+use crate::utils;
+use crate::{Context, Error};
+
+/// Lists which are edited at runtime by owner commands and saved to disk
+#[derive(Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AccessLists {
+    pub allowed_channels: Vec<String>, // Channel IDs which accept control commands, all channels if empty
+    pub blocked_users: Vec<String>,    // User IDs whose commands are ignored
+}
+
+pub struct Access {
+    path: PathBuf,
+    lists: Mutex<AccessLists>,
+}
+
+impl Access {
+    pub fn load(path: &str) -> Self {
+        let path = PathBuf::from(path);
+        Access {
+            lists: Mutex::new(utils::load_state(&path, "access lists")),
+            path,
+        }
+    }
+
+    pub fn lists(&self) -> AccessLists {
+        self.lists.lock().unwrap().clone()
+    }
+
+    pub fn is_blocked(&self, user_id: u64) -> bool {
+        self.lists.lock().unwrap().blocked_users.contains(&user_id.to_string())
+    }
+
+    pub fn is_channel_allowed(&self, channel_id: u64) -> bool {
+        let lists = self.lists.lock().unwrap();
+        lists.allowed_channels.is_empty() || lists.allowed_channels.contains(&channel_id.to_string())
+    }
+
+    /// Returns false if the user was already blocked
+    pub fn block(&self, user_id: u64) -> bool {
+        self.edit(|lists| add_id(&mut lists.blocked_users, user_id))
+    }
+
+    /// Returns false if the user wasn't blocked
+    pub fn unblock(&self, user_id: u64) -> bool {
+        self.edit(|lists| remove_id(&mut lists.blocked_users, user_id))
+    }
+
+    /// Returns false if the channel was already allowed
+    pub fn allow_channel(&self, channel_id: u64) -> bool {
+        self.edit(|lists| add_id(&mut lists.allowed_channels, channel_id))
+    }
+
+    /// Returns false if the channel wasn't allowed
+    pub fn disallow_channel(&self, channel_id: u64) -> bool {
+        self.edit(|lists| remove_id(&mut lists.allowed_channels, channel_id))
+    }
+
+    /// Applies an edit and saves the lists if anything changed
+    fn edit(&self, edit: impl FnOnce(&mut AccessLists) -> bool) -> bool {
+        let mut lists = self.lists.lock().unwrap();
+        let changed = edit(&mut lists);
+        if changed {
+            utils::save_state(&self.path, "access lists", &*lists);
+        }
+        changed
+    }
+}
+
+fn add_id(ids: &mut Vec<String>, id: u64) -> bool {
+    let id = id.to_string();
+    if ids.contains(&id) {
+        return false;
+    }
+    ids.push(id);
+    true
+}
+
+fn remove_id(ids: &mut Vec<String>, id: u64) -> bool {
+    let id = id.to_string();
+    let length = ids.len();
+    ids.retain(|existing| *existing != id);
+    ids.len() != length
+}
+
+/// Global command check, ignores blocked users and keeps control commands in allowed channels
+pub async fn check(ctx: Context<'_>) -> Result<bool, Error> {
+    if ctx.data().access.is_blocked(ctx.author().id.get()) {
+        println!("{} ({}) -> Ignored command {}, user is blocked", ctx.author().name, ctx.author().id, ctx.command().qualified_name);
+        return Ok(false);
+    }
+
+    // Admin commands work anywhere so the lists can always be fixed
+    let is_control = matches!(permissions::required_capability(&ctx.command().qualified_name), Some(capability) if capability != Capability::Admin);
+    if !is_control || ctx.data().access.is_channel_allowed(ctx.channel_id().get()) {
+        return Ok(true);
+    }
+
+    let reply_embed = CreateEmbed::default()
+        .title("Wrong channel")
+        .field("**Caller**", format!("{} ({})\nControl commands aren't accepted in this channel.", ctx.author().name, ctx.author().id), false)
+        .color(Color::RED)
+        .thumbnail(ctx.author().face())
+        .timestamp(Timestamp::now());
+
+    ctx.send(CreateReply::default().ephemeral(true).embed(reply_embed)).await?;
+    Ok(false)
+}
@@ -0,0 +1,123 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+use log::error;
+use poise::serenity_prelude::{self as serenity, ChannelId, ReactionType};
+
+use crate::chatbox::{self, moderation};
+use crate::chatbox::manager::{ChatboxMessage, Priority};
+use crate::Data;
+
+#[derive(Default)]
+struct State {
+    channel: Option<ChannelId>, // Messages here are relayed while the bridge is running
+    paused: bool,
+}
+
+/// Relays messages from one Discord channel into the chatbox
+#[derive(Default)]
+pub struct Bridge {
+    state: Mutex<State>,
+}
+
+impl Bridge {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts relaying `channel`, replacing any channel relayed before, and unpauses
+    pub fn start(&self, channel: ChannelId) {
+        let mut state = self.state.lock().unwrap();
+        state.channel = Some(channel);
+        state.paused = false;
+    }
+
+    /// Stops relaying, returns the channel that was relayed
+    pub fn stop(&self) -> Option<ChannelId> {
+        self.state.lock().unwrap().channel.take()
+    }
+
+    /// Pauses or resumes relaying, returns whether the bridge is now paused
+    pub fn toggle_pause(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        state.paused = !state.paused;
+        state.paused
+    }
+
+    /// The relayed channel and whether the bridge is paused
+    pub fn status(&self) -> (Option<ChannelId>, bool) {
+        let state = self.state.lock().unwrap();
+        (state.channel, state.paused)
+    }
+
+    fn relays(&self, channel: ChannelId) -> bool {
+        let state = self.state.lock().unwrap();
+        state.channel == Some(channel) && !state.paused
+    }
+}
+
+/// The chatbox line for a relayed message
+fn relay_line(prefix: &str, name: &str, text: &str) -> String {
+    format!("{}{}: {}", prefix, name, text)
+}
+
+/// Relays messages in the bridged channel to the chatbox as "name: text"
+pub async fn handle_message(ctx: &serenity::Context, data: &Data, message: &serenity::Message) {
+    if message.author.bot || !data.bridge.relays(message.channel_id) || data.access.is_blocked(message.author.id.get()) {
+        return;
+    }
+
+    let text = chatbox::resolve_message(&ctx.cache, message);
+    if text.is_empty() {
+        return;
+    }
+
+    // The name and prefix are shown too, so the whole line goes through moderation
+    let (line, min_display, filtered) = {
+        let config = data.config.lock().unwrap();
+        let prefix = config.bridge.prefixes.get(&message.author.id.to_string()).map_or("", String::as_str);
+        let line = relay_line(prefix, message.author.display_name(), &text);
+        let filtered = moderation::filter(&line, &config.moderation);
+        (line, Duration::from_secs(config.bridge.min_display_secs), filtered)
+    };
+    let line = match filtered {
+        Ok(line) => line,
+        Err(rejection) => {
+            println!("{} ({}) -> Bridged message rejected: {}", message.author.name, message.author.id, rejection);
+            moderation::report(&ctx.http, &data.config, &message.author, &line, &rejection).await;
+            if let Err(e) = message.react(ctx, ReactionType::Unicode("🚫".to_owned())).await {
+                error!("Failed to react to bridged message: {}", e);
+            }
+            return;
+        }
+    };
+
+    for page in chatbox::paginate(&line) {
+        data.chatbox.submit(ChatboxMessage::new(page, Priority::Normal, min_display));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chatbox::moderation::Rejection;
+    use crate::config::Moderation;
+
+    fn moderation() -> Moderation {
+        let mut settings = Moderation { blocklist: vec!["badword".to_owned()], ..Moderation::default() };
+        settings.compile().unwrap();
+        settings
+    }
+
+    #[test]
+    fn blocked_words_in_the_name_reject_the_line() {
+        let line = relay_line("", "B4dw0rd", "hello everyone");
+        assert!(matches!(moderation::filter(&line, &moderation()), Err(Rejection::BlockedWord(_))));
+    }
+
+    #[test]
+    fn names_and_prefixes_are_cleaned_up_like_text() {
+        let line = relay_line("[mod] ", "discord.gg/spam", "hiiiiiiii");
+        assert_eq!(moderation::filter(&line, &moderation()).unwrap(), "[mod] [link] hiiii");
+    }
+}
@@ -0,0 +1,126 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use log::{error, warn};
+use notify::{RecursiveMode, Watcher};
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+
+use crate::config::Config;
+use crate::utils;
+use super::manager::{ChatboxManager, ChatboxMessage, Priority};
+use super::moderation;
+
+// Writers often save in several steps, changes within this window are read once
+const SETTLE_TIME: Duration = Duration::from_millis(200);
+
+// How often the config is checked for a new path while nothing else happens
+const CONFIG_CHECK: Duration = Duration::from_secs(5);
+
+// Pages of the file are queued under this key prefix so new content replaces them
+const KEY_PREFIX: &str = "file:";
+
+/// Queues the file's content as chatbox pages, replacing any pages of it still waiting
+fn send(manager: &ChatboxManager, content: &str, dwell: Duration, max_pages: usize, config: &Mutex<Config>) {
+    manager.withdraw(KEY_PREFIX);
+    let filtered = moderation::filter(content, &config.lock().unwrap().moderation);
+    let content = match filtered {
+        Ok(content) => content,
+        Err(rejection) => {
+            warn!("Chatbox file content rejected: {}", rejection);
+            return;
+        }
+    };
+    for (index, page) in super::paginate(&content).into_iter().take(max_pages).enumerate() {
+        manager.submit(ChatboxMessage::new(page, Priority::Low, dwell).key(&format!("{}{}", KEY_PREFIX, index)));
+    }
+}
+
+/// Sends the watched file to the chatbox whenever it changes, and every `repeat_secs` if set
+pub async fn run(manager: Arc<ChatboxManager>, config: Arc<Mutex<Config>>) {
+    let (sender, mut events) = mpsc::unbounded_channel();
+    let mut watcher = match notify::recommended_watcher(move |event| {
+        let _ = sender.send(event);
+    }) {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            error!("Failed to start the chatbox file watcher: {}", e);
+            return;
+        }
+    };
+
+    let mut watched: Option<PathBuf> = None;
+    let mut content = String::new();
+    let mut last_sent = Instant::now();
+
+    loop {
+        let (path, repeat, dwell, max_pages) = {
+            let config = config.lock().unwrap();
+            let settings = &config.file_source;
+            let path = (settings.enabled && !settings.path.trim().is_empty()).then(|| PathBuf::from(settings.path.trim()));
+            (path, Duration::from_secs(settings.repeat_secs), Duration::from_secs(config.chatbox.page_dwell_secs), config.chatbox.max_pages)
+        };
+
+        // Follow the configured path, sending the new file straight away
+        let mut changed = false;
+        if path != watched {
+            if let Some(old) = &watched {
+                let _ = watcher.unwatch(&utils::watch_dir(old));
+            }
+            if let Some(new) = &path {
+                if let Err(e) = watcher.watch(&utils::watch_dir(new), RecursiveMode::NonRecursive) {
+                    warn!("Failed to watch {}: {}", new.display(), e);
+                }
+            }
+            watched = path.clone();
+            content.clear();
+            changed = true;
+        }
+        let Some(path) = path else {
+            manager.withdraw(KEY_PREFIX);
+            tokio::time::sleep(CONFIG_CHECK).await;
+            continue;
+        };
+
+        let wait = if repeat.is_zero() { CONFIG_CHECK } else { repeat.saturating_sub(last_sent.elapsed()).min(CONFIG_CHECK) };
+        if !changed {
+            tokio::select! {
+                Some(event) = events.recv() => {
+                    let concerns_file = match event {
+                        Ok(event) => event.paths.iter().any(|changed| changed.file_name() == path.file_name()),
+                        Err(e) => {
+                            warn!("Chatbox file watcher error: {}", e);
+                            false
+                        }
+                    };
+                    if concerns_file {
+                        tokio::time::sleep(SETTLE_TIME).await;
+                        while events.try_recv().is_ok() {}
+                        changed = true;
+                    }
+                }
+                _ = tokio::time::sleep(wait) => {}
+            }
+        }
+
+        if changed {
+            // A missing file counts as empty, it may be written later
+            let new_content = fs::read_to_string(&path).map(|text| text.trim().to_owned()).unwrap_or_default();
+            if new_content == content {
+                continue;
+            }
+            content = new_content;
+        } else if repeat.is_zero() || last_sent.elapsed() < repeat || content.is_empty() {
+            continue;
+        }
+
+        if content.is_empty() {
+            manager.withdraw(KEY_PREFIX);
+        } else {
+            send(&manager, &content, dwell, max_pages, &config);
+        }
+        last_sent = Instant::now();
+    }
+}
@@ -0,0 +1,223 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::sync::Notify;
+use tokio::time::Instant;
+
+use crate::config::Config;
+use crate::vrc_client::client::Client;
+use crate::vrc_client::traits::Input;
+use super::{MAX_CHARS, MAX_LINES};
+
+/// Which pending message is shown first, higher goes first
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    Low,    // Status lines shown while nothing else is waiting
+    Normal, // Messages sent by users
+}
+
+#[derive(Clone, Debug)]
+pub struct ChatboxMessage {
+    pub text: String,
+    pub priority: Priority,
+    pub min_display: Duration, // Nothing replaces the message until this has passed
+    pub key: Option<String>,   // A newer message with the same key replaces this one while it is pending
+    pub typing: bool,          // Show the typing indicator while this message waits its turn
+}
+
+impl ChatboxMessage {
+    pub fn new(text: String, priority: Priority, min_display: Duration) -> Self {
+        ChatboxMessage {
+            text,
+            priority,
+            min_display,
+            key: None,
+            typing: false,
+        }
+    }
+
+    pub fn key(mut self, key: &str) -> Self {
+        self.key = Some(key.to_owned());
+        self
+    }
+
+    pub fn typing(mut self, typing: bool) -> Self {
+        self.typing = typing;
+        self
+    }
+
+    /// Whether both messages fit in the chatbox at once, joined by a line break
+    fn fits_with(&self, other: &ChatboxMessage) -> bool {
+        let chars = self.text.chars().count() + 1 + other.text.chars().count();
+        let lines = self.text.lines().count() + other.text.lines().count();
+        chars <= MAX_CHARS && lines <= MAX_LINES
+    }
+}
+
+#[derive(Default)]
+struct State {
+    pending: Vec<ChatboxMessage>, // In the order they were submitted
+}
+
+impl State {
+    /// Index of the oldest message with the highest priority
+    fn next_index(&self) -> Option<usize> {
+        let priority = self.pending.iter().map(|message| message.priority).max()?;
+        self.pending.iter().position(|message| message.priority == priority)
+    }
+
+    /// Takes the next message, joined with the messages behind it while they fit
+    /// Keyed messages are never joined since they stand for a single changing line
+    fn take_next(&mut self) -> Option<ChatboxMessage> {
+        let mut message = self.pending.remove(self.next_index()?);
+        if message.key.is_some() {
+            return Some(message);
+        }
+
+        while let Some(index) = self.next_index() {
+            let candidate = &self.pending[index];
+            if candidate.priority != message.priority || candidate.key.is_some() || !message.fits_with(candidate) {
+                break;
+            }
+            let candidate = self.pending.remove(index);
+            message.text = format!("{}\n{}", message.text, candidate.text);
+            message.min_display = message.min_display.max(candidate.min_display);
+        }
+        Some(message)
+    }
+}
+
+/// Owns /chatbox/input, every chatbox write goes through here
+/// Shown messages stay up for their minimum display time and sends are spaced out to stay under VRChat's rate limit
+#[derive(Default)]
+pub struct ChatboxManager {
+    state: Mutex<State>,
+    notify: Notify,
+}
+
+impl ChatboxManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues a message, replacing a pending message with the same key
+    /// The text must already have been through moderation::filter, senders filter it whole before paging so they can report rejections
+    pub fn submit(&self, message: ChatboxMessage) {
+        {
+            let mut state = self.state.lock().unwrap();
+            if let Some(key) = &message.key {
+                state.pending.retain(|pending| pending.key.as_ref() != Some(key));
+            }
+            state.pending.push(message);
+        }
+        self.notify.notify_one();
+    }
+
+    /// Drops pending messages whose key starts with `key_prefix`
+    pub fn withdraw(&self, key_prefix: &str) {
+        self.state.lock().unwrap().pending.retain(|pending| !pending.key.as_ref().is_some_and(|key| key.starts_with(key_prefix)));
+    }
+
+    /// Number of messages waiting to be shown
+    pub fn pending(&self) -> usize {
+        self.state.lock().unwrap().pending.len()
+    }
+
+    /// Whether the next message wants the typing indicator while it waits
+    fn next_wants_typing(&self) -> bool {
+        let state = self.state.lock().unwrap();
+        state.next_index().is_some_and(|index| state.pending[index].typing)
+    }
+}
+
+/// Sends pending messages to the chatbox for as long as the bot runs
+pub async fn run(manager: Arc<ChatboxManager>, vrc_client: Arc<Client>, config: Arc<Mutex<Config>>) {
+    let mut ready_at = Instant::now(); // When the shown message may be replaced
+    let mut typing = false;
+
+    loop {
+        if manager.pending() == 0 {
+            manager.notify.notified().await;
+            continue;
+        }
+
+        // Wait out the shown message, with the typing indicator on if the next message asks for it
+        if Instant::now() < ready_at {
+            let wants_typing = manager.next_wants_typing() && config.lock().unwrap().chatbox.typing_indicator;
+            if wants_typing && !typing {
+                vrc_client.chatbox_typing(true);
+                typing = true;
+            }
+            tokio::time::sleep_until(ready_at).await;
+        }
+
+        let Some(message) = manager.state.lock().unwrap().take_next() else {
+            continue;
+        };
+        vrc_client.chatbox_message(&message.text);
+        if typing {
+            vrc_client.chatbox_typing(false);
+            typing = false;
+        }
+
+        let min_interval = Duration::from_millis(config.lock().unwrap().chatbox.min_interval_ms);
+        ready_at = Instant::now() + message.min_display.max(min_interval);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(text: &str, priority: Priority) -> ChatboxMessage {
+        ChatboxMessage::new(text.to_owned(), priority, Duration::from_secs(1))
+    }
+
+    /// Texts in the order they would be shown
+    fn drain(manager: &ChatboxManager) -> Vec<String> {
+        let mut state = manager.state.lock().unwrap();
+        std::iter::from_fn(|| state.take_next()).map(|message| message.text).collect()
+    }
+
+    #[test]
+    fn higher_priority_goes_first() {
+        let manager = ChatboxManager::new();
+        manager.submit(message("status", Priority::Low).key("status"));
+        manager.submit(message("first", Priority::Normal).key("a"));
+        manager.submit(message("second", Priority::Normal).key("b"));
+        assert_eq!(drain(&manager), ["first", "second", "status"]);
+    }
+
+    #[test]
+    fn keyed_messages_replace_pending_ones() {
+        let manager = ChatboxManager::new();
+        manager.submit(message("0:10", Priority::Normal).key("timers"));
+        manager.submit(message("hello", Priority::Normal).key("say"));
+        manager.submit(message("0:08", Priority::Normal).key("timers"));
+        assert_eq!(manager.pending(), 2);
+        assert_eq!(drain(&manager), ["hello", "0:08"]);
+    }
+
+    #[test]
+    fn short_messages_of_the_same_priority_are_joined() {
+        let manager = ChatboxManager::new();
+        manager.submit(message("a: hi", Priority::Normal));
+        manager.submit(message("status", Priority::Low));
+        manager.submit(message("b: hey", Priority::Normal));
+        manager.submit(message("countdown", Priority::Normal).key("timers"));
+        manager.submit(message("c: yo", Priority::Normal));
+        assert_eq!(drain(&manager), ["a: hi\nb: hey", "countdown", "c: yo", "status"]);
+    }
+
+    #[test]
+    fn joined_messages_stay_within_the_chatbox() {
+        let manager = ChatboxManager::new();
+        let long = "x".repeat(MAX_CHARS - 5);
+        let lines = ["line"; MAX_LINES].join("\n");
+        manager.submit(message(&long, Priority::Normal));
+        manager.submit(message("too long", Priority::Normal));
+        manager.submit(message(&lines, Priority::Low));
+        manager.submit(message("extra", Priority::Low));
+        assert_eq!(drain(&manager), [long, "too long".to_owned(), lines, "extra".to_owned()]);
+    }
+}
@@ -0,0 +1,168 @@
+pub mod file_source;
+pub mod manager;
+pub mod moderation;
+#[cfg(target_os = "linux")]
+pub mod now_playing;
+pub mod status;
+pub mod widgets;
+
+use poise::serenity_prelude::{self as serenity, ContentSafeOptions, GuildId, Message};
+
+// VRChat truncates chatbox messages past these limits
+pub const MAX_CHARS: usize = 144;
+pub const MAX_LINES: usize = 9;
+
+/// Replaces custom emoji such as <:wave:123> or <a:wave:123> with :wave:
+fn resolve_custom_emoji(text: &str) -> String {
+    let mut output = String::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find('<') {
+        output.push_str(&rest[..start]);
+        let candidate = &rest[start..];
+        let resolved = candidate.find('>').and_then(|end| {
+            let inner = &candidate[1..end];
+            let inner = inner.strip_prefix('a').unwrap_or(inner);
+            let (name, id) = inner.strip_prefix(':')?.split_once(':')?;
+            let is_emoji = !name.is_empty() && !id.is_empty() && id.chars().all(|c| c.is_ascii_digit());
+            is_emoji.then(|| (format!(":{}:", name), end + 1))
+        });
+
+        match resolved {
+            Some((emoji, length)) => {
+                output.push_str(&emoji);
+                rest = &candidate[length..];
+            }
+            None => {
+                output.push('<');
+                rest = &candidate[1..];
+            }
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+/// Turns a Discord message into plain chatbox text
+/// Mentions become names, custom emoji become :name: and attachments are summarised
+pub fn resolve_message(cache: &serenity::Cache, message: &Message) -> String {
+    let mut parts: Vec<String> = Vec::new();
+
+    let content = resolve_custom_emoji(&message.content_safe(cache));
+    if !content.trim().is_empty() {
+        parts.push(content.trim().to_owned());
+    }
+
+    for attachment in &message.attachments {
+        let kind = match attachment.content_type.as_deref() {
+            Some(content_type) if content_type.starts_with("image/") => "image",
+            Some(content_type) if content_type.starts_with("video/") => "video",
+            Some(content_type) if content_type.starts_with("audio/") => "audio",
+            _ => "file",
+        };
+        parts.push(format!("[{}: {}]", kind, attachment.filename));
+    }
+    for sticker in &message.sticker_items {
+        parts.push(format!("[sticker: {}]", sticker.name));
+    }
+
+    parts.join("\n")
+}
+
+/// Turns text typed into a command into plain chatbox text, resolving mentions and custom emoji
+pub fn resolve_text(cache: &serenity::Cache, guild_id: Option<GuildId>, text: &str) -> String {
+    let mut options = ContentSafeOptions::default();
+    if let Some(guild_id) = guild_id {
+        options = options.display_as_member_from(guild_id);
+    }
+    resolve_custom_emoji(&serenity::content_safe(cache, text, &options, &[])).trim().to_owned()
+}
+
+/// Splits text into pages of at most MAX_CHARS characters and MAX_LINES lines
+/// Words are kept whole unless a single word is longer than a page
+pub fn paginate(text: &str) -> Vec<String> {
+    let mut pages = Vec::new();
+    let mut page = String::new();
+    let mut chars = 0;
+    let mut lines = 1;
+
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        let mut first_word = true;
+        for word in line.split_whitespace() {
+            // Words longer than a page are broken up
+            let word_chars: Vec<char> = word.chars().collect();
+            for piece in word_chars.chunks(MAX_CHARS) {
+                let piece: String = piece.iter().collect();
+                let separator = match (page.is_empty(), first_word) {
+                    (true, _) => "",
+                    (false, true) => "\n",
+                    (false, false) => " ",
+                };
+                let new_lines = lines + usize::from(separator == "\n");
+                if !page.is_empty() && (chars + separator.len() + piece.chars().count() > MAX_CHARS || new_lines > MAX_LINES) {
+                    pages.push(std::mem::take(&mut page));
+                    lines = 1;
+                    page.push_str(&piece);
+                } else {
+                    lines = new_lines;
+                    page.push_str(separator);
+                    page.push_str(&piece);
+                }
+                chars = page.chars().count();
+                first_word = false;
+            }
+        }
+    }
+
+    if !page.is_empty() {
+        pages.push(page);
+    }
+    pages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn custom_emoji_become_names() {
+        assert_eq!(resolve_custom_emoji("hi <:wave:123> and <a:dance:456>"), "hi :wave: and :dance:");
+        assert_eq!(resolve_custom_emoji("1 < 2 and <:nope:abc> <b>"), "1 < 2 and <:nope:abc> <b>");
+        assert_eq!(resolve_custom_emoji("<:unclosed:123"), "<:unclosed:123");
+    }
+
+    #[test]
+    fn short_text_is_a_single_page() {
+        assert_eq!(paginate("  hello   world  "), vec!["hello world"]);
+        assert_eq!(paginate("one\n\n two"), vec!["one\ntwo"]);
+        assert!(paginate(" \n ").is_empty());
+    }
+
+    #[test]
+    fn pages_stay_under_the_character_limit_and_keep_words_whole() {
+        let words = vec!["word"; 60];
+        let pages = paginate(&words.join(" "));
+        assert!(pages.len() > 1);
+        for page in &pages {
+            assert!(page.chars().count() <= MAX_CHARS);
+            assert!(page.split(' ').all(|word| word == "word"));
+        }
+        assert_eq!(pages.join(" "), words.join(" "));
+    }
+
+    #[test]
+    fn pages_stay_under_the_line_limit() {
+        let text = (1..=20).map(|line| line.to_string()).collect::<Vec<String>>().join("\n");
+        let pages = paginate(&text);
+        assert_eq!(pages.len(), 3);
+        assert!(pages.iter().all(|page| page.lines().count() <= MAX_LINES));
+        assert_eq!(pages[1].lines().next(), Some("10"));
+    }
+
+    #[test]
+    fn long_words_are_split() {
+        let word = "a".repeat(MAX_CHARS * 2 + 10);
+        let pages = paginate(&format!("hi {}", word));
+        assert_eq!(pages, vec!["hi".to_owned(), "a".repeat(MAX_CHARS), "a".repeat(MAX_CHARS), "a".repeat(10)]);
+    }
+}
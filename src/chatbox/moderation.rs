@@ -0,0 +1,236 @@
+use std::fmt;
+use std::sync::LazyLock;
+
+use log::error;
+use poise::serenity_prelude::{self as serenity, ChannelId, Color, CreateEmbed, CreateMessage, Timestamp, User};
+use regex::{Regex, RegexSet};
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::config::{Config, Moderation};
+use crate::utils;
+
+static LINKS: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\b(?:https?://|www\.|discord\.gg/|discord\.com/invite/)\S+").expect("link pattern is valid")
+});
+
+/// Why a message was kept out of the chatbox
+#[derive(Debug)]
+pub enum Rejection {
+    BlockedWord(String),
+    BlockedPattern(String),
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rejection::BlockedWord(word) => write!(f, "Contains the blocked word \"{}\"", word),
+            Rejection::BlockedPattern(pattern) => write!(f, "Matches the blocked pattern `{}`", pattern),
+        }
+    }
+}
+
+/// Maps characters that look like ASCII letters, including common leetspeak, to those letters
+fn confusable(c: char) -> char {
+    match c {
+        'а' | 'А' | 'α' | 'Α' | '@' | '4' => 'a',
+        'в' | 'В' | 'β' | 'Β' => 'b',
+        'с' | 'С' | 'ϲ' => 'c',
+        'е' | 'Е' | 'ε' | 'Ε' | '3' => 'e',
+        'н' | 'Н' | 'Η' => 'h',
+        'і' | 'І' | 'ι' | 'Ι' | '1' => 'i',
+        'ј' | 'Ј' => 'j',
+        'к' | 'К' | 'κ' | 'Κ' => 'k',
+        'м' | 'М' | 'Μ' => 'm',
+        'ν' | 'Ν' => 'n',
+        'о' | 'О' | 'ο' | 'Ο' | '0' => 'o',
+        'р' | 'Р' | 'ρ' | 'Ρ' => 'p',
+        'ѕ' | 'Ѕ' | '$' | '5' => 's',
+        'т' | 'Т' | 'τ' | 'Τ' | '7' => 't',
+        'υ' => 'u',
+        'у' | 'У' | 'Υ' => 'y',
+        'х' | 'Х' | 'χ' | 'Χ' => 'x',
+        'Ζ' => 'z',
+        c => c,
+    }
+}
+
+/// Zero-width and formatting characters used to split words without showing it
+fn is_invisible(c: char) -> bool {
+    matches!(c, '\u{00AD}' | '\u{200B}'..='\u{200F}' | '\u{2060}'..='\u{2064}' | '\u{FEFF}')
+}
+
+/// Folds text into lowercase ASCII lookalikes so blocked words can't be dodged with
+/// fullwidth letters, accents, other scripts or leetspeak. Only used for matching.
+pub fn normalize(text: &str) -> String {
+    text.nfkd()
+        .filter(|c| !is_combining_mark(*c) && !is_invisible(*c))
+        .map(confusable)
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Replaces links with [link]
+fn strip_links(text: &str) -> String {
+    LINKS.replace_all(text, "[link]").into_owned()
+}
+
+/// Shortens runs of the same character to at most `max` characters
+fn limit_repeats(text: &str, max: usize) -> String {
+    let mut output = String::new();
+    let mut previous = None;
+    let mut run = 0;
+    for c in text.chars() {
+        run = if previous == Some(c) { run + 1 } else { 1 };
+        previous = Some(c);
+        if run <= max {
+            output.push(c);
+        }
+    }
+    output
+}
+
+/// Blocked words and patterns compiled once when the config is loaded
+#[derive(Default)]
+pub struct Rules {
+    words: RegexSet,
+    blocked: Vec<String>, // The blocklist entry behind each of `words`
+    patterns: RegexSet,
+}
+
+impl Rules {
+    /// Compiles the blocklist and patterns, invalid patterns are an error
+    pub fn new(settings: &Moderation) -> Result<Self, regex::Error> {
+        let blocked: Vec<String> = settings.blocklist.iter()
+            .filter(|word| !normalize(word).trim().is_empty())
+            .cloned()
+            .collect();
+        let words = RegexSet::new(blocked.iter().map(|word| format!(r"\b{}\b", regex::escape(normalize(word).trim()))))?;
+        let patterns = RegexSet::new(&settings.patterns)?;
+        Ok(Rules { words, blocked, patterns })
+    }
+}
+
+/// Runs text through the filter, returning the text to show or why it was rejected
+/// Links and long repeats are cleaned up, blocked words and patterns reject the whole message
+pub fn filter(text: &str, settings: &Moderation) -> Result<String, Rejection> {
+    if !settings.enabled {
+        return Ok(text.to_owned());
+    }
+
+    let rules = &settings.rules;
+    let normalized = normalize(text);
+    if let Some(index) = rules.words.matches(&normalized).iter().next() {
+        return Err(Rejection::BlockedWord(rules.blocked[index].clone()));
+    }
+    let matched = rules.patterns.matches(text).into_iter().chain(rules.patterns.matches(&normalized)).min();
+    if let Some(index) = matched {
+        return Err(Rejection::BlockedPattern(rules.patterns.patterns()[index].clone()));
+    }
+
+    let mut text = text.to_owned();
+    if settings.strip_links {
+        text = strip_links(&text);
+    }
+    if settings.max_repeat > 0 {
+        text = limit_repeats(&text, settings.max_repeat);
+    }
+    Ok(text)
+}
+
+/// Posts a rejected message to the logging channel, if one is configured
+pub async fn report(http: &serenity::Http, config: &std::sync::Mutex<Config>, user: &User, text: &str, rejection: &Rejection) {
+    let channel = config.lock().unwrap().system.vrc_client_logging_channel.parse::<u64>();
+    let Some(channel) = channel.ok().filter(|channel| *channel != 0) else {
+        return;
+    };
+
+    let embed = CreateEmbed::default()
+        .title("Chatbox message rejected")
+        .field("**Sender**", format!("{} ({})\nReason: {}", user.name, user.id, rejection), false)
+        .field("**Text**", utils::truncate(text, 1024), false) // Discord limits fields to 1024 characters
+        .color(Color::RED)
+        .thumbnail(user.face())
+        .timestamp(Timestamp::now());
+
+    if let Err(e) = ChannelId::new(channel).send_message(http, CreateMessage::new().embed(embed)).await {
+        error!("Failed to report rejected chatbox message: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(blocklist: &[&str], patterns: &[&str]) -> Moderation {
+        let mut settings = Moderation {
+            blocklist: blocklist.iter().map(|word| word.to_string()).collect(),
+            patterns: patterns.iter().map(|pattern| pattern.to_string()).collect(),
+            ..Moderation::default()
+        };
+        settings.compile().unwrap();
+        settings
+    }
+
+    #[test]
+    fn normalize_folds_leetspeak_and_lookalikes() {
+        assert_eq!(normalize("h3ll0 w0rld"), "hello world");
+        assert_eq!(normalize("$TR1NG"), "string");
+        assert_eq!(normalize("ｈｅｌｌｏ"), "hello");
+        assert_eq!(normalize("héllö"), "hello");
+        assert_eq!(normalize("рaураl"), "paypal");
+    }
+
+    #[test]
+    fn normalize_drops_zero_width_characters() {
+        assert_eq!(normalize("b\u{200B}a\u{200D}d\u{FEFF}"), "bad");
+        assert_eq!(normalize("so\u{00AD}ft"), "soft");
+    }
+
+    #[test]
+    fn blocked_words_are_matched_after_normalizing() {
+        let settings = settings(&["badword"], &[]);
+        for text in ["a badword here", "B4DW0RD", "bad\u{200B}word", "ｂａｄｗｏｒｄ"] {
+            assert!(matches!(filter(text, &settings), Err(Rejection::BlockedWord(word)) if word == "badword"), "{}", text);
+        }
+        assert_eq!(filter("badwords are whole words only", &settings).unwrap(), "badwords are whole words only");
+    }
+
+    #[test]
+    fn blocked_patterns_reject_the_message() {
+        let settings = settings(&[], &[r"free\s+nitro"]);
+        assert!(filter("nitro for free", &settings).is_ok());
+        assert!(matches!(filter("FREE NITRO", &settings), Err(Rejection::BlockedPattern(_))));
+        assert!(matches!(filter("get free  nitro", &settings), Err(Rejection::BlockedPattern(_))));
+        assert!(matches!(filter("fr33 n1tro", &settings), Err(Rejection::BlockedPattern(_))));
+    }
+
+    #[test]
+    fn invalid_patterns_fail_to_compile() {
+        let mut settings = Moderation { patterns: vec!["(unclosed".to_owned()], ..Moderation::default() };
+        assert!(settings.compile().is_err());
+    }
+
+    #[test]
+    fn links_are_stripped() {
+        let settings = settings(&[], &[]);
+        assert_eq!(filter("join discord.gg/abc now", &settings).unwrap(), "join [link] now");
+        assert_eq!(filter("see https://example.com/x and www.example.org", &settings).unwrap(), "see [link] and [link]");
+        assert_eq!(filter("no links here.com", &settings).unwrap(), "no links here.com");
+    }
+
+    #[test]
+    fn long_repeats_are_shortened() {
+        let settings = settings(&[], &[]);
+        assert_eq!(filter("hiiiiiiii!!!!!!", &settings).unwrap(), "hiiii!!!!");
+        assert_eq!(limit_repeats("aaabbb", 2), "aabb");
+        assert_eq!(limit_repeats("abab", 1), "abab");
+    }
+
+    #[test]
+    fn disabled_filter_passes_text_through() {
+        let mut settings = settings(&["badword"], &[]);
+        settings.enabled = false;
+        assert_eq!(filter("badword https://example.com", &settings).unwrap(), "badword https://example.com");
+    }
+}
@@ -0,0 +1,240 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use log::warn;
+use zbus::fdo::DBusProxy;
+use zbus::proxy::CacheProperties;
+use zbus::zvariant::{OwnedValue, Value};
+use zbus::Connection;
+
+use crate::config::Config;
+use super::status::Sources;
+use super::widgets::NowPlaying;
+
+// MPRIS players register under this prefix, e.g. org.mpris.MediaPlayer2.spotify
+const MPRIS_PREFIX: &str = "org.mpris.MediaPlayer2.";
+
+// How long to wait before trying the session bus again after it couldn't be reached
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(30);
+
+#[zbus::proxy(interface = "org.mpris.MediaPlayer2.Player", default_path = "/org/mpris/MediaPlayer2")]
+trait Player {
+    #[zbus(property)]
+    fn playback_status(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn metadata(&self) -> zbus::Result<HashMap<String, OwnedValue>>;
+
+    #[zbus(property)]
+    fn position(&self) -> zbus::Result<i64>;
+}
+
+/// MPRIS microseconds, which players send as either signed or unsigned integers
+fn microseconds(value: &Value<'_>) -> Option<Duration> {
+    match value {
+        Value::I64(micros) => u64::try_from(*micros).ok().map(Duration::from_micros),
+        Value::U64(micros) => Some(Duration::from_micros(*micros)),
+        _ => None,
+    }
+}
+
+/// Reads the current track of a player, None while it is stopped or has no title
+/// `destination` is the player's bus name, which peer-to-peer connections ignore
+pub async fn read_player(connection: &Connection, destination: &str) -> zbus::Result<Option<NowPlaying>> {
+    // Position never emits change signals, so nothing may be cached
+    let player = PlayerProxy::builder(connection)
+        .destination(destination.to_owned())?
+        .cache_properties(CacheProperties::No)
+        .build()
+        .await?;
+
+    let status = player.playback_status().await?;
+    if status == "Stopped" {
+        return Ok(None);
+    }
+
+    let metadata = player.metadata().await?;
+    let title = metadata.get("xesam:title")
+        .and_then(|title| <&str>::try_from(&**title).ok())
+        .unwrap_or_default()
+        .to_owned();
+    if title.is_empty() {
+        return Ok(None);
+    }
+    let artist = match metadata.get("xesam:artist").map(|artist| &**artist) {
+        Some(Value::Array(artists)) => artists.iter()
+            .filter_map(|artist| <&str>::try_from(artist).ok())
+            .collect::<Vec<&str>>()
+            .join(", "),
+        Some(Value::Str(artist)) => artist.to_string(),
+        _ => String::new(),
+    };
+    let length = metadata.get("mpris:length").and_then(|length| microseconds(length));
+    // Some players don't implement Position, the track is still worth showing
+    let position = player.position().await.ok()
+        .and_then(|position| u64::try_from(position).ok())
+        .map_or(Duration::ZERO, Duration::from_micros);
+
+    Ok(Some(NowPlaying {
+        title,
+        artist,
+        position,
+        length,
+        playing: status == "Playing",
+    }))
+}
+
+/// Bus name of the configured player, or of the first player found when `player` is empty
+async fn find_player(connection: &Connection, player: &str) -> zbus::Result<Option<String>> {
+    let names = DBusProxy::new(connection).await?.list_names().await?;
+    let mut players = names.iter()
+        .map(|name| name.to_string())
+        .filter(|name| name.starts_with(MPRIS_PREFIX));
+    if player.is_empty() {
+        return Ok(players.next());
+    }
+    Ok(players.find(|name| name[MPRIS_PREFIX.len()..].starts_with(player)))
+}
+
+/// Keeps `sources` up to date with the session bus' current track while the widget is enabled
+pub async fn run(sources: Arc<Sources>, config: Arc<Mutex<Config>>) {
+    let mut connection: Option<Connection> = None;
+    loop {
+        let (enabled, player, interval) = {
+            let config = config.lock().unwrap();
+            let settings = &config.widgets.now_playing;
+            (settings.enabled, settings.player.clone(), Duration::from_secs(settings.poll_secs.max(1)))
+        };
+        if !enabled {
+            sources.set_now_playing(None);
+            tokio::time::sleep(interval).await;
+            continue;
+        }
+
+        if connection.is_none() {
+            match Connection::session().await {
+                Ok(session) => connection = Some(session),
+                Err(e) => {
+                    warn!("Now playing widget couldn't reach the session bus: {}", e);
+                    tokio::time::sleep(RECONNECT_INTERVAL).await;
+                    continue;
+                }
+            }
+        }
+        let Some(session) = &connection else {
+            continue;
+        };
+
+        let track = match find_player(session, &player).await {
+            Ok(Some(name)) => read_player(session, &name).await,
+            Ok(None) => Ok(None),
+            Err(e) => Err(e),
+        };
+        match track {
+            Ok(track) => sources.set_now_playing(track),
+            Err(e) => {
+                warn!("Now playing widget failed to read the player: {}", e);
+                sources.set_now_playing(None);
+                // The session bus may have restarted, connect again on the next poll
+                connection = None;
+            }
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use tokio::net::UnixStream;
+    use zbus::connection::Builder;
+    use zbus::Guid;
+
+    const FAKE_PLAYER: &str = "org.mpris.MediaPlayer2.fake";
+
+    /// Serves just enough of org.mpris.MediaPlayer2.Player to be read
+    struct FakePlayer {
+        status: String,
+        title: String,
+        artists: Vec<String>,
+        length_micros: i64,
+        position_micros: i64,
+    }
+
+    #[zbus::interface(name = "org.mpris.MediaPlayer2.Player")]
+    impl FakePlayer {
+        #[zbus(property)]
+        fn playback_status(&self) -> String {
+            self.status.clone()
+        }
+
+        #[zbus(property)]
+        fn metadata(&self) -> HashMap<String, OwnedValue> {
+            HashMap::from([
+                ("xesam:title".to_owned(), OwnedValue::try_from(Value::from(self.title.clone())).unwrap()),
+                ("xesam:artist".to_owned(), OwnedValue::try_from(Value::from(self.artists.clone())).unwrap()),
+                ("mpris:length".to_owned(), OwnedValue::from(self.length_micros)),
+            ])
+        }
+
+        #[zbus(property)]
+        fn position(&self) -> i64 {
+            self.position_micros
+        }
+    }
+
+    /// Connects a client to a fake player over a private peer-to-peer bus
+    async fn connect(player: FakePlayer) -> (Connection, Connection) {
+        let (server_stream, client_stream) = UnixStream::pair().unwrap();
+        let server = Builder::unix_stream(server_stream)
+            .server(Guid::generate())
+            .unwrap()
+            .p2p()
+            .serve_at("/org/mpris/MediaPlayer2", player)
+            .unwrap()
+            .build();
+        let client = Builder::unix_stream(client_stream).p2p().build();
+        let (server, client) = tokio::join!(server, client);
+        (server.unwrap(), client.unwrap())
+    }
+
+    fn fake_player(status: &str) -> FakePlayer {
+        FakePlayer {
+            status: status.to_owned(),
+            title: "Song".to_owned(),
+            artists: vec!["First".to_owned(), "Second".to_owned()],
+            length_micros: 200_000_000,
+            position_micros: 50_000_000,
+        }
+    }
+
+    #[tokio::test]
+    async fn reads_track_from_fake_player() {
+        let (_server, client) = connect(fake_player("Playing")).await;
+
+        let track = read_player(&client, FAKE_PLAYER).await.unwrap();
+        assert_eq!(track, Some(NowPlaying {
+            title: "Song".to_owned(),
+            artist: "First, Second".to_owned(),
+            position: Duration::from_secs(50),
+            length: Some(Duration::from_secs(200)),
+            playing: true,
+        }));
+    }
+
+    #[tokio::test]
+    async fn paused_player_is_still_shown() {
+        let (_server, client) = connect(fake_player("Paused")).await;
+
+        let track = read_player(&client, FAKE_PLAYER).await.unwrap().unwrap();
+        assert!(!track.playing);
+    }
+
+    #[tokio::test]
+    async fn stopped_player_shows_nothing() {
+        let (_server, client) = connect(fake_player("Stopped")).await;
+
+        assert_eq!(read_player(&client, FAKE_PLAYER).await.unwrap(), None);
+    }
+}
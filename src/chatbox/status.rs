@@ -0,0 +1,155 @@
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::config::{Config, Widgets};
+use crate::control::ControlLock;
+use crate::scheduler::Scheduler;
+use crate::utils;
+use log::warn;
+
+use super::manager::{ChatboxManager, ChatboxMessage, Priority};
+use super::moderation;
+use super::widgets::{self, NowPlaying, SystemStats};
+
+/// Placeholders templates may use, with a short description for /chatbox list
+pub const PLACEHOLDERS: [(&str, &str); 13] = [
+    ("{controller}", "who has control, or nobody"),
+    ("{queue}", "actions waiting in the queue"),
+    ("{uptime}", "how long the bot has been running"),
+    ("{time}", "local time of day"),
+    ("{cpu}", "CPU usage in percent"),
+    ("{ram}", "RAM usage in percent"),
+    ("{ram_used}", "RAM in use, in GB"),
+    ("{ram_total}", "total RAM, in GB"),
+    ("{actions}", "actions performed today"),
+    ("{title}", "title of the playing track"),
+    ("{artist}", "artist of the playing track"),
+    ("{progress}", "progress bar of the playing track"),
+    ("{playback}", "▶️ while playing, ⏸️ while paused"),
+];
+
+/// Everything placeholders are filled in from
+pub struct Sources {
+    pub control: Arc<ControlLock>,
+    pub scheduler: Arc<Scheduler>,
+    pub started: Instant,
+    pub system: SystemStats,
+    pub now_playing: Mutex<Option<NowPlaying>>, // Kept up to date by the now playing widget
+}
+
+impl Sources {
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))] // Only the Linux now playing widget sets it
+    pub fn set_now_playing(&self, track: Option<NowPlaying>) {
+        *self.now_playing.lock().unwrap() = track;
+    }
+
+    pub fn now_playing(&self) -> Option<NowPlaying> {
+        self.now_playing.lock().unwrap().clone()
+    }
+}
+
+/// Replaces every placeholder in `template` with its value
+pub fn render(template: &str, values: &[(&str, String)]) -> String {
+    values.iter()
+        .fold(template.to_owned(), |text, (placeholder, value)| text.replace(placeholder, value))
+        .trim()
+        .to_owned()
+}
+
+/// Local time in `time_format`, or ? if the format is invalid
+fn format_time(time_format: &str) -> String {
+    // Writing returns an error for invalid formats where to_string would panic
+    let mut time = String::new();
+    match write!(time, "{}", chrono::Local::now().format(time_format)) {
+        Ok(()) => time,
+        Err(_) => "?".to_owned(),
+    }
+}
+
+/// Current value of every placeholder, stats which can't be read show as ?
+pub fn values(sources: &Sources, widgets: &Widgets) -> Vec<(&'static str, String)> {
+    let controller = sources.control.driver().map_or("nobody".to_owned(), |driver| driver.user_name);
+    let cpu = sources.system.cpu_percent().map_or("?".to_owned(), |cpu| format!("{:.0}", cpu));
+    let (ram, ram_used, ram_total) = match sources.system.memory_gb() {
+        Some((used, total)) if total > 0.0 => (format!("{:.0}", 100.0 * used / total), format!("{:.1}", used), format!("{:.1}", total)),
+        _ => ("?".to_owned(), "?".to_owned(), "?".to_owned()),
+    };
+    let (title, artist, progress, playback) = match sources.now_playing() {
+        Some(track) => (
+            track.title,
+            track.artist,
+            widgets::progress_bar(track.position, track.length, widgets.now_playing.bar_width),
+            if track.playing { "▶️" } else { "⏸️" }.to_owned(),
+        ),
+        None => (String::new(), String::new(), String::new(), String::new()),
+    };
+
+    vec![
+        ("{controller}", controller),
+        ("{queue}", sources.scheduler.snapshot().waiting.len().to_string()),
+        ("{uptime}", utils::format_duration(sources.started.elapsed().as_secs())),
+        ("{time}", format_time(&widgets.time_format)),
+        ("{cpu}", cpu),
+        ("{ram}", ram),
+        ("{ram_used}", ram_used),
+        ("{ram_total}", ram_total),
+        ("{actions}", sources.scheduler.executed_today().to_string()),
+        ("{title}", title),
+        ("{artist}", artist),
+        ("{progress}", progress),
+        ("{playback}", playback),
+    ]
+}
+
+/// Templates rotated through: the status templates, or `options.message` for older configs, then enabled widgets
+/// The now playing widget is left out while nothing is playing
+pub fn rotation(config: &Config, sources: &Sources) -> Vec<String> {
+    let mut rotation = if config.chatbox.templates.is_empty() {
+        vec![config.options.message.clone()]
+    } else {
+        config.chatbox.templates.clone()
+    };
+    rotation.extend(config.widgets.enabled().into_iter()
+        .map(|(name, widget)| widgets::template_or_default(name, &widget.template).to_owned()));
+    let now_playing = &config.widgets.now_playing;
+    if now_playing.enabled && sources.now_playing().is_some() {
+        rotation.push(widgets::template_or_default("now_playing", &now_playing.template).to_owned());
+    }
+    rotation
+}
+
+/// Rotates through the status templates and widgets, shown whenever no user messages are waiting
+/// The control banner takes over while someone has control and `control.banner` is on
+pub async fn run(manager: Arc<ChatboxManager>, config: Arc<Mutex<Config>>, sources: Arc<Sources>) {
+    let mut index: usize = 0;
+    loop {
+        let (enabled, interval, rotation, widgets, show_banner) = {
+            let config = config.lock().unwrap();
+            (config.chatbox.enabled, Duration::from_secs(config.chatbox.interval_secs.max(1)), rotation(&config, &sources), config.widgets.clone(), config.control.banner)
+        };
+
+        let text = match sources.control.banner() {
+            Some(banner) if show_banner => banner,
+            _ => {
+                // Skip over templates which render to nothing so the interval isn't wasted
+                let values = values(&sources, &widgets);
+                let rendered: Vec<String> = rotation.iter()
+                    .map(|template| render(template, &values))
+                    .filter(|text| !text.is_empty())
+                    .collect();
+                index = index.wrapping_add(1);
+                if rendered.is_empty() { String::new() } else { rendered[index % rendered.len()].clone() }
+            }
+        };
+        // Track titles and driver names come from outside, so status lines are filtered like user messages
+        if enabled && !text.is_empty() {
+            let filtered = moderation::filter(&text, &config.lock().unwrap().moderation);
+            match filtered {
+                Ok(text) => manager.submit(ChatboxMessage::new(text, Priority::Low, Duration::ZERO).key("status")),
+                Err(rejection) => warn!("Status line rejected: {}", rejection),
+            }
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
@@ -0,0 +1,126 @@
+use std::fs;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Built-in widgets as (name, default template), shown in this order after the status templates
+pub const DEFAULT_TEMPLATES: [(&str, &str); 6] = [
+    ("clock", "🕒 {time}"),
+    ("uptime", "⏱️ Up {uptime}"),
+    ("system", "💻 CPU {cpu}% | RAM {ram_used}/{ram_total} GB"),
+    ("actions", "🎮 {actions} actions today"),
+    ("queue", "📋 {queue} in queue"),
+    ("now_playing", "🎵 {artist} - {title}\n{playback} {progress}"),
+];
+
+/// Template of a built-in widget, falling back to its default when left empty
+pub fn template_or_default<'a>(name: &str, template: &'a str) -> &'a str {
+    if !template.trim().is_empty() {
+        return template;
+    }
+    DEFAULT_TEMPLATES.iter()
+        .find(|(widget, _)| *widget == name)
+        .map_or("", |(_, template)| template)
+}
+
+/// Cumulative CPU time from the first line of /proc/stat, in clock ticks
+#[derive(Clone, Copy, Default)]
+struct CpuTimes {
+    idle: u64,
+    total: u64,
+}
+
+fn read_cpu_times() -> Option<CpuTimes> {
+    let stat = fs::read_to_string("/proc/stat").ok()?;
+    let fields: Vec<u64> = stat.lines().next()?
+        .strip_prefix("cpu ")?
+        .split_whitespace()
+        .filter_map(|field| field.parse().ok())
+        .collect();
+    // user nice system idle iowait irq softirq steal, idle and iowait count as idle
+    let idle = fields.get(3)? + fields.get(4).unwrap_or(&0);
+    let total = fields.iter().take(8).sum();
+    Some(CpuTimes { idle, total })
+}
+
+/// Used and total memory in kB from /proc/meminfo
+fn read_memory() -> Option<(u64, u64)> {
+    let meminfo = fs::read_to_string("/proc/meminfo").ok()?;
+    let field = |name: &str| {
+        meminfo.lines()
+            .find_map(|line| line.strip_prefix(name))
+            .and_then(|value| value.split_whitespace().next())
+            .and_then(|value| value.parse::<u64>().ok())
+    };
+    let total = field("MemTotal:")?;
+    let available = field("MemAvailable:")?;
+    Some((total.saturating_sub(available), total))
+}
+
+/// Reads CPU and RAM usage from /proc, only available on Linux
+#[derive(Default)]
+pub struct SystemStats {
+    previous: Mutex<CpuTimes>, // CPU usage is measured between two reads
+}
+
+impl SystemStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// CPU usage in percent since the last read, or since boot on the first read
+    pub fn cpu_percent(&self) -> Option<f64> {
+        let current = read_cpu_times()?;
+        let mut previous = self.previous.lock().unwrap();
+        let idle = current.idle.saturating_sub(previous.idle);
+        let total = current.total.saturating_sub(previous.total);
+        *previous = current;
+        if total == 0 {
+            return Some(0.0);
+        }
+        Some(100.0 * (total - idle.min(total)) as f64 / total as f64)
+    }
+
+    /// Used and total RAM in GB
+    pub fn memory_gb(&self) -> Option<(f64, f64)> {
+        let (used, total) = read_memory()?;
+        Some((used as f64 / 1_048_576.0, total as f64 / 1_048_576.0))
+    }
+}
+
+/// The track a player is on
+#[derive(Clone, Debug, PartialEq)]
+pub struct NowPlaying {
+    pub title: String,
+    pub artist: String,
+    pub position: Duration,
+    pub length: Option<Duration>,
+    pub playing: bool, // False while paused
+}
+
+/// Formats a duration as m:ss
+fn format_clock(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+/// Text progress bar such as "1:23 ▬▬▬▬◉───── 3:45", just the position when the length is unknown
+pub fn progress_bar(position: Duration, length: Option<Duration>, width: usize) -> String {
+    let Some(length) = length.filter(|length| !length.is_zero()) else {
+        return format_clock(position);
+    };
+    let filled = ((position.as_secs_f64() / length.as_secs_f64()) * width as f64).round() as usize;
+    let filled = filled.min(width);
+    format!("{} {}◉{} {}", format_clock(position), "▬".repeat(filled), "─".repeat(width - filled), format_clock(length))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn progress_bar_fills_with_position() {
+        assert_eq!(progress_bar(Duration::from_secs(50), Some(Duration::from_secs(200)), 8), "0:50 ▬▬◉────── 3:20");
+        assert_eq!(progress_bar(Duration::from_secs(300), Some(Duration::from_secs(200)), 4), "5:00 ▬▬▬▬◉ 3:20");
+        assert_eq!(progress_bar(Duration::from_secs(61), None, 8), "1:01");
+    }
+}
@@ -0,0 +1,93 @@
+use poise::serenity_prelude::{self as serenity, CreateEmbed, Color, Timestamp};
+use poise::CreateReply;
+use crate::{Context, Error};
+
+/// Sends the result of an access list edit
+async fn reply_edit(ctx: Context<'_>, title: &str, changed: bool, unchanged: &str) -> Result<(), Error> {
+    let is_ephemeral = ctx.data().config.lock().unwrap().system.ephemeral_admin_commands;
+
+    let reply_embed = if changed {
+        CreateEmbed::default()
+            .title(title.to_owned())
+            .color(Color::DARK_GREEN)
+    } else {
+        CreateEmbed::default()
+            .title(unchanged.to_owned())
+            .color(Color::ORANGE)
+    }
+        .field("**Caller**".to_owned(), format!("{} ({})", ctx.author().name, ctx.author().id), false)
+        .thumbnail(ctx.author().face())
+        .timestamp(Timestamp::now());
+
+    ctx.send(CreateReply::default().ephemeral(is_ephemeral).embed(reply_embed)).await?;
+    Ok(())
+}
+
+/// Ignores every command from a user
+#[poise::command(prefix_command, slash_command, check = "crate::permissions::owners_only")]
+pub async fn block(
+    ctx: Context<'_>,
+    #[description = "User to block"] user: serenity::User,
+) -> Result<(), Error> {
+    let changed = ctx.data().access.block(user.id.get());
+    reply_edit(ctx, &format!("Blocked {}", user.name), changed, &format!("{} is already blocked", user.name)).await
+}
+
+/// Accepts commands from a blocked user again
+#[poise::command(prefix_command, slash_command, check = "crate::permissions::owners_only")]
+pub async fn unblock(
+    ctx: Context<'_>,
+    #[description = "User to unblock"] user: serenity::User,
+) -> Result<(), Error> {
+    let changed = ctx.data().access.unblock(user.id.get());
+    reply_edit(ctx, &format!("Unblocked {}", user.name), changed, &format!("{} isn't blocked", user.name)).await
+}
+
+/// Accepts control commands in a channel (all channels are accepted while none are allowed)
+#[poise::command(prefix_command, slash_command, check = "crate::permissions::owners_only")]
+pub async fn allow_channel(
+    ctx: Context<'_>,
+    #[description = "Channel to allow"] channel: serenity::Channel,
+) -> Result<(), Error> {
+    let changed = ctx.data().access.allow_channel(channel.id().get());
+    reply_edit(ctx, &format!("Allowed {}", channel), changed, &format!("{} is already allowed", channel)).await
+}
+
+/// Stops accepting control commands in a channel
+#[poise::command(prefix_command, slash_command, check = "crate::permissions::owners_only")]
+pub async fn disallow_channel(
+    ctx: Context<'_>,
+    #[description = "Channel to disallow"] channel: serenity::Channel,
+) -> Result<(), Error> {
+    let changed = ctx.data().access.disallow_channel(channel.id().get());
+    reply_edit(ctx, &format!("Disallowed {}", channel), changed, &format!("{} isn't allowed", channel)).await
+}
+
+/// Shows the allowed channels and blocked users
+#[poise::command(prefix_command, slash_command, check = "crate::permissions::owners_only")]
+pub async fn access(ctx: Context<'_>) -> Result<(), Error> {
+    let lists = ctx.data().access.lists();
+    let is_ephemeral = ctx.data().config.lock().unwrap().system.ephemeral_admin_commands;
+
+    let allowed_channels = if lists.allowed_channels.is_empty() {
+        "Every channel".to_owned()
+    } else {
+        lists.allowed_channels.iter().map(|id| format!("<#{}>", id)).collect::<Vec<String>>().join("\n")
+    };
+    let blocked_users = if lists.blocked_users.is_empty() {
+        "Nobody".to_owned()
+    } else {
+        lists.blocked_users.iter().map(|id| format!("<@{}>", id)).collect::<Vec<String>>().join("\n")
+    };
+
+    let reply_embed = CreateEmbed::default()
+        .title("Access lists".to_owned())
+        .field("**Allowed channels**".to_owned(), allowed_channels, false)
+        .field("**Blocked users**".to_owned(), blocked_users, false)
+        .color(Color::BLURPLE)
+        .thumbnail(ctx.author().face())
+        .timestamp(Timestamp::now());
+
+    ctx.send(CreateReply::default().ephemeral(is_ephemeral).embed(reply_embed)).await?;
+    Ok(())
+}
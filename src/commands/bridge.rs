@@ -0,0 +1,58 @@
+use poise::serenity_prelude::{CreateEmbed, Color, Timestamp, ChannelId};
+use poise::CreateReply;
+use crate::{Context, Error};
+
+fn bridge_embed(ctx: Context<'_>, title: &str, color: Color) -> CreateEmbed {
+    let status = match ctx.data().bridge.status() {
+        (Some(channel), true) => format!("Paused, relaying <#{}> once resumed", channel),
+        (Some(channel), false) => format!("Relaying <#{}>", channel),
+        (None, _) => "Not running".to_owned(),
+    };
+
+    CreateEmbed::default()
+        .title(title.to_owned())
+        .field("**Caller**", format!("{} ({})\n{}", ctx.author().name, ctx.author().id, status), false)
+        .color(color)
+        .thumbnail(ctx.author().face())
+        .timestamp(Timestamp::now())
+}
+
+/// Relays a Discord channel into the VRChat chatbox
+#[poise::command(
+    prefix_command,
+    slash_command,
+    subcommands("bridge_start", "bridge_stop", "bridge_pause"),
+    subcommand_required
+)]
+pub async fn bridge(_: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Starts relaying every message in a channel to the chatbox
+#[poise::command(prefix_command, slash_command, rename = "start")]
+pub async fn bridge_start(
+    ctx: Context<'_>,
+    #[description = "Channel to relay"]
+    #[channel_types("Text")]
+    channel: ChannelId,
+) -> Result<(), Error> {
+    ctx.data().bridge.start(channel);
+    ctx.send(CreateReply::default().embed(bridge_embed(ctx, "Bridge started", Color::DARK_GREEN))).await?;
+    Ok(())
+}
+
+/// Stops relaying messages to the chatbox
+#[poise::command(prefix_command, slash_command, rename = "stop")]
+pub async fn bridge_stop(ctx: Context<'_>) -> Result<(), Error> {
+    let title = if ctx.data().bridge.stop().is_some() { "Bridge stopped" } else { "Bridge wasn't running" };
+    ctx.send(CreateReply::default().embed(bridge_embed(ctx, title, Color::DARK_GREY))).await?;
+    Ok(())
+}
+
+/// Pauses or resumes the bridge without forgetting its channel
+#[poise::command(prefix_command, slash_command, rename = "pause")]
+pub async fn bridge_pause(ctx: Context<'_>) -> Result<(), Error> {
+    let title = if ctx.data().bridge.toggle_pause() { "Bridge paused" } else { "Bridge resumed" };
+    ctx.send(CreateReply::default().embed(bridge_embed(ctx, title, Color::BLURPLE))).await?;
+    Ok(())
+}
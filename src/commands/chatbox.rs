@@ -0,0 +1,237 @@
+use std::time::Duration;
+
+use poise::serenity_prelude::{self as serenity, CreateEmbed, Color, Timestamp};
+use poise::CreateReply;
+use crate::chatbox;
+use crate::chatbox::manager::{ChatboxMessage, Priority};
+use crate::chatbox::moderation;
+use crate::chatbox::{status, widgets};
+use crate::config::{edit, Config};
+use crate::utils;
+use crate::{Context, Error};
+
+/// Splits text into pages and shows them in the chatbox, `detail` is added to the reply
+async fn send_text(ctx: Context<'_>, text: String, detail: String) -> Result<(), Error> {
+    let filtered = moderation::filter(&text, &ctx.data().config.lock().unwrap().moderation);
+    let text = match filtered {
+        Ok(text) => text,
+        Err(rejection) => {
+            println!("{} ({}) -> Chatbox message rejected: {}", ctx.author().name, ctx.author().id, rejection);
+            moderation::report(ctx.http(), &ctx.data().config, ctx.author(), &text, &rejection).await;
+            let reply_embed = CreateEmbed::default()
+                .title("Message rejected")
+                .field("**Caller**", format!("{} ({})\nReason: {}", ctx.author().name, ctx.author().id, rejection), false)
+                .color(Color::RED)
+                .thumbnail(ctx.author().face())
+                .timestamp(Timestamp::now());
+            ctx.send(CreateReply::default().ephemeral(true).embed(reply_embed)).await?;
+            return Ok(());
+        }
+    };
+    let pages = chatbox::paginate(&text);
+    let (dwell, max_pages) = {
+        let config = ctx.data().config.lock().unwrap();
+        (Duration::from_secs(config.chatbox.page_dwell_secs), config.chatbox.max_pages)
+    };
+
+    let refusal = if pages.is_empty() {
+        Some(("Nothing to send", "There is no text to show.".to_owned()))
+    } else if pages.len() > max_pages {
+        Some(("Message too long", format!("That would take {} pages, the limit is {}.", pages.len(), max_pages)))
+    } else {
+        None
+    };
+    if let Some((title, reason)) = refusal {
+        let reply_embed = CreateEmbed::default()
+            .title(title)
+            .field("**Caller**", format!("{} ({})\n{}", ctx.author().name, ctx.author().id, reason), false)
+            .color(Color::RED)
+            .thumbnail(ctx.author().face())
+            .timestamp(Timestamp::now());
+        ctx.send(CreateReply::default().ephemeral(true).embed(reply_embed)).await?;
+        return Ok(());
+    }
+
+    let reply_embed = CreateEmbed::default()
+        .title("Sent to chatbox")
+        .field("**Caller**", format!("{} ({})\n{}Pages: {}", ctx.author().name, ctx.author().id, detail, pages.len()), false)
+        .field("**Text**", utils::truncate(&text, 1024), false) // Discord limits fields to 1024 characters
+        .color(Color::DARK_GREEN)
+        .thumbnail(ctx.author().face())
+        .timestamp(Timestamp::now());
+
+    // Each page stays up for the dwell time, with the typing indicator on while the next one waits
+    for page in pages {
+        ctx.data().chatbox.submit(ChatboxMessage::new(page, Priority::Normal, dwell).typing(true));
+    }
+    ctx.send(CreateReply::default().ephemeral(true).embed(reply_embed)).await?;
+    Ok(())
+}
+
+/// Shows text in the VRChat chatbox, long text is split into pages
+#[poise::command(prefix_command, slash_command)]
+pub async fn say(
+    ctx: Context<'_>,
+    #[description = "Text to show in the chatbox"]
+    #[rest]
+    text: String,
+) -> Result<(), Error> {
+    let text = chatbox::resolve_text(ctx.cache(), ctx.guild_id(), &text);
+    send_text(ctx, text, String::new()).await
+}
+
+/// Sends the selected message to the VRChat chatbox
+#[poise::command(context_menu_command = "Send to VRChat chatbox")]
+pub async fn send_to_chatbox(ctx: Context<'_>, message: serenity::Message) -> Result<(), Error> {
+    let text = chatbox::resolve_message(ctx.cache(), &message);
+    send_text(ctx, text, format!("Message by: {}\n", message.author.name)).await
+}
+
+/// Embed showing the status rotation after a change, each template with a preview
+fn rotation_embed(ctx: Context<'_>, title: &str) -> CreateEmbed {
+    let (enabled, interval, templates, widgets, settings) = {
+        let config = ctx.data().config.lock().unwrap();
+        let mut widgets: Vec<(&str, String)> = config.widgets.enabled().into_iter()
+            .map(|(name, widget)| (name, widgets::template_or_default(name, &widget.template).to_owned()))
+            .collect();
+        if config.widgets.now_playing.enabled {
+            widgets.push(("now_playing", widgets::template_or_default("now_playing", &config.widgets.now_playing.template).to_owned()));
+        }
+        (config.chatbox.enabled, config.chatbox.interval_secs, config.chatbox.templates.clone(), widgets, config.widgets.clone())
+    };
+    let values = status::values(&ctx.data().status, &settings);
+
+    let listed = if templates.is_empty() {
+        "No templates, `options.message` is shown instead".to_owned()
+    } else {
+        templates.iter().enumerate()
+            .map(|(index, template)| format!("**{}.** `{}`\n→ {}", index + 1, template, status::render(template, &values)))
+            .collect::<Vec<String>>()
+            .join("\n")
+    };
+    let widgets = if widgets.is_empty() {
+        "None enabled".to_owned()
+    } else {
+        widgets.iter()
+            .map(|(name, template)| format!("**{}** → {}", name, status::render(template, &values)))
+            .collect::<Vec<String>>()
+            .join("\n")
+    };
+    let placeholders = status::PLACEHOLDERS.iter()
+        .map(|(placeholder, description)| format!("`{}` {}", placeholder, description))
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    CreateEmbed::default()
+        .title(title.to_owned())
+        .field("**Caller**", format!("{} ({})\nEnabled: {}\nInterval: {}s", ctx.author().name, ctx.author().id, enabled, interval), false)
+        .field("**Templates**", utils::truncate(&listed, 1024), false)
+        .field("**Widgets**", utils::truncate(&widgets, 1024), false)
+        .field("**Placeholders**", utils::truncate(&placeholders, 1024), false)
+        .color(Color::BLURPLE)
+        .thumbnail(ctx.author().face())
+        .timestamp(Timestamp::now())
+}
+
+/// Manages the status lines rotated through in the chatbox
+#[poise::command(
+    prefix_command,
+    slash_command,
+    subcommands("chatbox_list", "chatbox_add", "chatbox_remove", "chatbox_interval", "chatbox_enable", "chatbox_disable"),
+    subcommand_required
+)]
+pub async fn chatbox(_: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Lists the status templates with a preview of each
+#[poise::command(prefix_command, slash_command, rename = "list")]
+pub async fn chatbox_list(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.send(CreateReply::default().embed(rotation_embed(ctx, "Chatbox status rotation"))).await?;
+    Ok(())
+}
+
+/// Changes a chatbox field and saves it to the config file like /config set, then shows the rotation
+/// `value` is given the current config and returns the new value as TOML, or why nothing changed
+async fn save_field(
+    ctx: Context<'_>,
+    title: &str,
+    field: &str,
+    value: impl FnOnce(&Config) -> Result<String, String>,
+) -> Result<(), Error> {
+    let result = match Config::path() {
+        Some(file) => edit::update(&ctx.data().config, &file, field, value),
+        None => Err("No config file found".to_owned()),
+    };
+
+    let reply_embed = match result {
+        Ok(_) => rotation_embed(ctx, title),
+        Err(e) => CreateEmbed::default()
+            .title("Chatbox not changed")
+            .field("**Caller**", format!("{} ({})\nReason: {}", ctx.author().name, ctx.author().id, utils::truncate(&e, 900)), false)
+            .color(Color::RED)
+            .thumbnail(ctx.author().face())
+            .timestamp(Timestamp::now()),
+    };
+    ctx.send(CreateReply::default().embed(reply_embed)).await?;
+    Ok(())
+}
+
+/// The templates as a TOML array
+fn templates_value(templates: Vec<String>) -> String {
+    toml::Value::Array(templates.into_iter().map(toml::Value::String).collect()).to_string()
+}
+
+/// Adds a status template to the rotation
+#[poise::command(prefix_command, slash_command, rename = "add")]
+pub async fn chatbox_add(
+    ctx: Context<'_>,
+    #[description = "Template, e.g. {controller} has control | {time}"]
+    #[rest]
+    template: String,
+) -> Result<(), Error> {
+    save_field(ctx, "Template added", "chatbox.templates", |config| {
+        let mut templates = config.chatbox.templates.clone();
+        templates.push(template.trim().to_owned());
+        Ok(templates_value(templates))
+    }).await
+}
+
+/// Removes a status template from the rotation
+#[poise::command(prefix_command, slash_command, rename = "remove")]
+pub async fn chatbox_remove(
+    ctx: Context<'_>,
+    #[description = "Number of the template, as shown by /chatbox list"] number: usize,
+) -> Result<(), Error> {
+    save_field(ctx, "Template removed", "chatbox.templates", |config| {
+        let mut templates = config.chatbox.templates.clone();
+        if !(1..=templates.len()).contains(&number) {
+            return Err(format!("There is no template number {}, see /chatbox list", number));
+        }
+        templates.remove(number - 1);
+        Ok(templates_value(templates))
+    }).await
+}
+
+/// Sets how often the next status template is shown
+#[poise::command(prefix_command, slash_command, rename = "interval")]
+pub async fn chatbox_interval(
+    ctx: Context<'_>,
+    #[description = "Seconds between templates"]
+    #[min = 1]
+    seconds: u64,
+) -> Result<(), Error> {
+    save_field(ctx, "Interval changed", "chatbox.interval_secs", |_| Ok(seconds.max(1).to_string())).await
+}
+
+/// Turns the status rotation on
+#[poise::command(prefix_command, slash_command, rename = "enable")]
+pub async fn chatbox_enable(ctx: Context<'_>) -> Result<(), Error> {
+    save_field(ctx, "Status rotation enabled", "chatbox.enabled", |_| Ok("true".to_owned())).await
+}
+
+/// Turns the status rotation off, user messages are still shown
+#[poise::command(prefix_command, slash_command, rename = "disable")]
+pub async fn chatbox_disable(ctx: Context<'_>) -> Result<(), Error> {
+    save_field(ctx, "Status rotation disabled", "chatbox.enabled", |_| Ok("false".to_owned())).await
+}
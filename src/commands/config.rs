@@ -0,0 +1,95 @@
+use poise::serenity_prelude::{CreateEmbed, Color, Timestamp};
+use poise::CreateReply;
+use crate::config::{edit, Config};
+use crate::utils;
+use crate::{Context, Error};
+
+fn config_embed(ctx: Context<'_>, title: &str, detail: String, color: Color) -> CreateEmbed {
+    CreateEmbed::default()
+        .title(title.to_owned())
+        .field("**Caller**", format!("{} ({})", ctx.author().name, ctx.author().id), false)
+        .field("**Config**", utils::truncate(&detail, 1024), false) // Discord limits fields to 1024 characters
+        .color(color)
+        .thumbnail(ctx.author().face())
+        .timestamp(Timestamp::now())
+}
+
+async fn reply(ctx: Context<'_>, embed: CreateEmbed) -> Result<(), Error> {
+    let is_ephemeral = ctx.data().config.lock().unwrap().system.ephemeral_admin_commands;
+    ctx.send(CreateReply::default().ephemeral(is_ephemeral).embed(embed)).await?;
+    Ok(())
+}
+
+/// Shows and changes the bot's config
+#[poise::command(
+    prefix_command,
+    slash_command,
+    subcommands("config_get", "config_set", "config_reload"),
+    subcommand_required,
+    check = "crate::permissions::owners_only"
+)]
+pub async fn config(_: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Shows a config field or section, e.g. chatbox.interval_secs
+#[poise::command(prefix_command, slash_command, check = "crate::permissions::owners_only", rename = "get")]
+pub async fn config_get(
+    ctx: Context<'_>,
+    #[description = "Field or section, e.g. chatbox.interval_secs"]
+    path: String,
+) -> Result<(), Error> {
+    let value = ctx.data().config.lock().unwrap().get(path.trim());
+    let embed = match value {
+        Ok(value) => config_embed(ctx, "Config value", format!("`{}` = `{}`", path.trim(), value), Color::BLURPLE),
+        Err(e) => config_embed(ctx, "Config value not found", e, Color::RED),
+    };
+    reply(ctx, embed).await
+}
+
+/// Changes a config field and saves it to the config file
+#[poise::command(prefix_command, slash_command, check = "crate::permissions::owners_only", rename = "set")]
+pub async fn config_set(
+    ctx: Context<'_>,
+    #[description = "Field, e.g. chatbox.interval_secs"]
+    path: String,
+    #[description = "New value as TOML, text needs no quotes"]
+    #[rest]
+    value: String,
+) -> Result<(), Error> {
+    let field = path.trim();
+    let Some(file) = Config::path() else {
+        return reply(ctx, config_embed(ctx, "Config not changed", "No config file found".to_owned(), Color::RED)).await;
+    };
+
+    let result = edit::set(&ctx.data().config, &file, field, value.trim());
+
+    let embed = match result {
+        Ok((old, new)) => {
+            let old = old.map_or("(unset)".to_owned(), |old| old.to_string());
+            let mut detail = format!("`{}`: `{}` → `{}`\nSaved to {}", field, old, new, file.display());
+            if edit::is_overridden(field) {
+                detail.push_str(&format!("\n{} overrides the file, so this is undone on the next reload", edit::override_variable(field)));
+            }
+            config_embed(ctx, "Config changed", detail, Color::DARK_GREEN)
+        }
+        Err(e) => config_embed(ctx, "Config not changed", e, Color::RED),
+    };
+    reply(ctx, embed).await
+}
+
+/// Reloads the config file, which also happens on its own whenever the file is saved
+#[poise::command(prefix_command, slash_command, check = "crate::permissions::owners_only", rename = "reload")]
+pub async fn config_reload(ctx: Context<'_>) -> Result<(), Error> {
+    // The file is read before the config is locked, an invalid file leaves the running config untouched
+    let result = Config::path().ok_or_else(|| "No config file found".to_owned()).and_then(|path| Config::load(&path));
+    let embed = match result {
+        Ok(loaded) => {
+            let detail = loaded.to_string();
+            *ctx.data().config.lock().unwrap() = loaded;
+            config_embed(ctx, "Config reloaded", detail, Color::DARK_GREEN)
+        }
+        Err(e) => config_embed(ctx, "Config not reloaded", e, Color::RED),
+    };
+    reply(ctx, embed).await
+}
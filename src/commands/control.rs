@@ -0,0 +1,158 @@
+use std::time::Duration;
+
+use poise::serenity_prelude::{self as serenity, CreateEmbed, Color, Timestamp};
+use poise::CreateReply;
+use crate::control::TakeResult;
+use crate::{Context, Error};
+use crate::utils;
+
+/// Takes exclusive control of movement commands, or joins the waitlist while rotating
+#[poise::command(prefix_command, slash_command)]
+pub async fn takecontrol(
+    ctx: Context<'_>,
+    #[description = "Minutes to keep control for"] minutes: u64,
+) -> Result<(), Error> {
+    let max_minutes = ctx.data().config.lock().unwrap().control.max_minutes;
+    let minutes = minutes.clamp(1, max_minutes.max(1));
+
+    let result = ctx.data().control.take(ctx.author().id, ctx.author().name.clone(), Duration::from_secs(minutes * 60));
+
+    let reply_embed = match result {
+        TakeResult::Granted(duration) => CreateEmbed::default()
+            .title("You have control")
+            .field("**Driver**", format!("{} ({})\nDuration: {}", ctx.author().name, ctx.author().id, utils::format_duration(duration.as_secs())), false)
+            .color(Color::DARK_GREEN),
+        TakeResult::Extended(duration) => CreateEmbed::default()
+            .title("Control extended")
+            .field("**Driver**", format!("{} ({})\nDuration: {}", ctx.author().name, ctx.author().id, utils::format_duration(duration.as_secs())), false)
+            .color(Color::DARK_GREEN),
+        TakeResult::Waitlisted(position) => CreateEmbed::default()
+            .title("Added to the waitlist")
+            .field("**Caller**", format!("{} ({})\nWaitlist position: {}", ctx.author().name, ctx.author().id, position), false)
+            .color(Color::BLURPLE),
+        TakeResult::Refused(driver) => CreateEmbed::default()
+            .title("Control is taken")
+            .field("**Caller**", format!("{} ({})\n{}", ctx.author().name, ctx.author().id, driver), false)
+            .color(Color::RED),
+    }
+        .thumbnail(ctx.author().face())
+        .timestamp(Timestamp::now());
+
+    ctx.send(CreateReply::default().embed(reply_embed)).await?;
+    Ok(())
+}
+
+/// Gives up control, or leaves the waitlist
+#[poise::command(prefix_command, slash_command)]
+pub async fn releasecontrol(ctx: Context<'_>) -> Result<(), Error> {
+    let control = &ctx.data().control;
+    let is_owner = ctx.data().config.lock().unwrap().is_owner(ctx.author().id.get());
+
+    let reply_embed = match control.driver() {
+        // Owners may end anyone's turn
+        Some(driver) if driver.user_id == ctx.author().id || is_owner => {
+            let next = match control.release() {
+                Some(next) => format!("\nHanded off to: {}", next.user_name),
+                None => String::new(),
+            };
+            CreateEmbed::default()
+                .title("Control released")
+                .field("**Caller**", format!("{} ({})\nPrevious driver: {}{}", ctx.author().name, ctx.author().id, driver.user_name, next), false)
+                .color(Color::DARK_GREEN)
+        }
+        _ if control.leave_waitlist(ctx.author().id) => CreateEmbed::default()
+            .title("Left the waitlist")
+            .field("**Caller**", format!("{} ({})", ctx.author().name, ctx.author().id), false)
+            .color(Color::DARK_GREEN),
+        _ => CreateEmbed::default()
+            .title("You don't have control")
+            .field("**Caller**", format!("{} ({})", ctx.author().name, ctx.author().id), false)
+            .color(Color::ORANGE),
+    }
+        .thumbnail(ctx.author().face())
+        .timestamp(Timestamp::now());
+
+    ctx.send(CreateReply::default().embed(reply_embed)).await?;
+    Ok(())
+}
+
+/// Gives a user control immediately, overriding the current driver
+#[poise::command(prefix_command, slash_command, check = "crate::permissions::owners_only")]
+pub async fn forcecontrol(
+    ctx: Context<'_>,
+    #[description = "User to give control to"] user: serenity::User,
+    #[description = "Minutes to give control for"] minutes: u64,
+) -> Result<(), Error> {
+    let max_minutes = ctx.data().config.lock().unwrap().control.max_minutes;
+    let minutes = minutes.clamp(1, max_minutes.max(1));
+    ctx.data().control.force(user.id, user.name.clone(), Duration::from_secs(minutes * 60));
+
+    let reply_embed = CreateEmbed::default()
+        .title(format!("{} has control", user.name))
+        .field("**Caller**", format!("{} ({})\nDuration: {}", ctx.author().name, ctx.author().id, utils::format_duration(minutes * 60)), false)
+        .color(Color::DARK_GREEN)
+        .thumbnail(user.face())
+        .timestamp(Timestamp::now());
+
+    ctx.send(CreateReply::default().embed(reply_embed)).await?;
+    Ok(())
+}
+
+/// Turns automatic handoff rotation on or off
+#[poise::command(prefix_command, slash_command, check = "crate::permissions::owners_only")]
+pub async fn rotation(
+    ctx: Context<'_>,
+    #[description = "Whether to rotate control between waiting users"] enabled: bool,
+    #[description = "Minutes per turn (optional)"] minutes: Option<u64>,
+) -> Result<(), Error> {
+    let (turn_minutes, max_minutes) = {
+        let config = ctx.data().config.lock().unwrap();
+        (minutes.unwrap_or(config.control.turn_minutes), config.control.max_minutes)
+    };
+    let turn_minutes = turn_minutes.clamp(1, max_minutes.max(1));
+    let turn = enabled.then(|| Duration::from_secs(turn_minutes * 60));
+    ctx.data().control.set_rotation(turn);
+
+    let status = match turn {
+        Some(turn) => format!("Rotation on, {} per turn", utils::format_duration(turn.as_secs())),
+        None => "Rotation off, waitlist cleared".to_owned(),
+    };
+
+    let reply_embed = CreateEmbed::default()
+        .title(status)
+        .field("**Caller**", format!("{} ({})", ctx.author().name, ctx.author().id), false)
+        .color(Color::DARK_GREEN)
+        .thumbnail(ctx.author().face())
+        .timestamp(Timestamp::now());
+
+    ctx.send(CreateReply::default().embed(reply_embed)).await?;
+    Ok(())
+}
+
+/// Shows who has control and who is waiting
+#[poise::command(prefix_command, slash_command)]
+pub async fn driver(ctx: Context<'_>) -> Result<(), Error> {
+    let control = &ctx.data().control;
+
+    let driver = match control.driver() {
+        Some(driver) => driver.to_string(),
+        None => "Nobody, anyone may send movement commands".to_owned(),
+    };
+    let rotation = match control.rotation() {
+        Some(turn) => format!("On, {} per turn", utils::format_duration(turn.as_secs())),
+        None => "Off".to_owned(),
+    };
+    let waitlist = control.waitlist();
+    let waitlist = utils::format_list("Waitlist", &waitlist);
+
+    let reply_embed = CreateEmbed::default()
+        .title("Controller")
+        .field("**Driver**", driver, false)
+        .field("**Rotation**", format!("{}\n{}", rotation, waitlist.trim()), false)
+        .color(Color::BLURPLE)
+        .thumbnail(ctx.author().face())
+        .timestamp(Timestamp::now());
+
+    ctx.send(CreateReply::default().embed(reply_embed)).await?;
+    Ok(())
+}
@@ -0,0 +1,190 @@
+use std::time::{Duration, Instant};
+
+use log::error;
+use poise::serenity_prelude::{self as serenity, ButtonStyle, Color, ComponentInteractionDataKind, CreateActionRow, CreateButton, CreateEmbed, CreateInteractionResponse, CreateInteractionResponseMessage, CreateSelectMenu, CreateSelectMenuKind, CreateSelectMenuOption, EditMessage, Timestamp};
+use poise::CreateReply;
+use crate::commands::vrc::describe_action;
+use crate::dispatch::{self, Outcome, Submitter};
+use crate::text_control::TextInput;
+use crate::vrc_client::Action;
+use crate::{Context, Error};
+
+// Input buttons as (custom id suffix, label, input), the first five fill the first row
+const INPUT_BUTTONS: [(&str, &str, TextInput); 7] = [
+    ("forward", "⬆️ Forward", TextInput::Forward),
+    ("backward", "⬇️ Backward", TextInput::Backward),
+    ("left", "⬅️ Left", TextInput::Left),
+    ("right", "➡️ Right", TextInput::Right),
+    ("jump", "🦘 Jump", TextInput::Jump),
+    ("look_left", "↩️ Look left", TextInput::LookLeft),
+    ("look_right", "↪️ Look right", TextInput::LookRight),
+];
+
+/// Settings of one controller message, shared by everyone pressing it
+struct ControllerState {
+    duration: u64,
+    run: bool,
+    last: String, // Result of the last press
+}
+
+fn components(id: u64, state: &ControllerState, durations: &[u64], enabled: bool) -> Vec<CreateActionRow> {
+    let button = |suffix: &str, label: &str, style: ButtonStyle| {
+        CreateButton::new(format!("{}:{}", id, suffix))
+            .label(label.to_owned())
+            .style(style)
+            .disabled(!enabled)
+    };
+
+    let buttons: Vec<CreateButton> = INPUT_BUTTONS.iter()
+        .map(|(suffix, label, _)| button(suffix, label, ButtonStyle::Primary))
+        .collect();
+    let (first_row, looks) = buttons.split_at(5);
+    let mut second_row = looks.to_vec();
+    let run_style = if state.run { ButtonStyle::Success } else { ButtonStyle::Secondary };
+    second_row.push(button("run", if state.run { "🏃 Run: on" } else { "🏃 Run: off" }, run_style));
+    second_row.push(button("stop", "⏹️ Stop", ButtonStyle::Danger));
+
+    let options = durations.iter()
+        .map(|duration| CreateSelectMenuOption::new(format!("{}s", duration), duration.to_string()).default_selection(*duration == state.duration))
+        .collect();
+    let select = CreateSelectMenu::new(format!("{}:duration", id), CreateSelectMenuKind::String { options })
+        .placeholder("Duration")
+        .disabled(!enabled);
+
+    // Discord allows five buttons per row
+    vec![
+        CreateActionRow::Buttons(first_row.to_vec()),
+        CreateActionRow::Buttons(second_row),
+        CreateActionRow::SelectMenu(select),
+    ]
+}
+
+fn embed(ctx: Context<'_>, state: &ControllerState) -> CreateEmbed {
+    let holding = match ctx.data().scheduler.snapshot().running {
+        Some(running) => format!("{} for {}s ({})", describe_action(&running.action), running.action.duration, running.user_name),
+        None => "Nothing".to_owned(),
+    };
+
+    CreateEmbed::default()
+        .title("Controller")
+        .field("**Holding**", holding, false)
+        .field("**Settings**", format!("Duration: {}s\nRun: {}", state.duration, if state.run { "on" } else { "off" }), false)
+        .field("**Last press**", state.last.clone(), false)
+        .color(Color::BLURPLE)
+        .timestamp(Timestamp::now())
+}
+
+/// Posts an interactive controller with direction buttons, a duration menu, a run toggle and stop
+#[poise::command(prefix_command, slash_command)]
+pub async fn controller(ctx: Context<'_>) -> Result<(), Error> {
+    let (durations, timeout) = {
+        let config = ctx.data().config.lock().unwrap();
+        (config.controller.durations.clone(), Duration::from_secs(config.controller.timeout_minutes * 60))
+    };
+    let id = ctx.id();
+    let mut state = ControllerState {
+        duration: durations.first().copied().unwrap_or(1),
+        run: false,
+        last: "Nothing yet".to_owned(),
+    };
+
+    let reply = ctx.send(CreateReply::default()
+        .embed(embed(ctx, &state))
+        .components(components(id, &state, &durations, true))
+    ).await?;
+    // The interaction token expires after 15 minutes, the message itself can be edited with the bot token for as long as it exists
+    let mut message = reply.message().await?.into_owned();
+
+    // Every press on this message is handled until the controller times out
+    let ends = Instant::now() + timeout;
+    let mut running = ctx.data().scheduler.watch_running();
+    loop {
+        let press = tokio::select! {
+            // Keeps the Holding field current between presses, the scheduler outlives the controller
+            _ = running.changed() => {
+                let edit = EditMessage::new()
+                    .embed(embed(ctx, &state))
+                    .components(components(id, &state, &durations, true));
+                if let Err(e) = message.edit(ctx, edit).await {
+                    error!("Failed to refresh controller {}: {}", id, e);
+                }
+                continue;
+            }
+            press = serenity::ComponentInteractionCollector::new(ctx)
+                .filter(move |press| press.data.custom_id.starts_with(&format!("{}:", id)))
+                .timeout(ends.saturating_duration_since(Instant::now())) => press,
+        };
+        let Some(press) = press else {
+            break;
+        };
+        let suffix = press.data.custom_id.split_once(':').map_or("", |(_, suffix)| suffix);
+
+        match suffix {
+            "duration" => {
+                if let ComponentInteractionDataKind::StringSelect { values } = &press.data.kind {
+                    if let Some(duration) = values.first().and_then(|value| value.parse::<u64>().ok()) {
+                        state.duration = duration;
+                        state.last = format!("{} set the duration to {}s", press.user.name, duration);
+                    }
+                }
+            }
+            "run" => {
+                state.run = !state.run;
+                state.last = format!("{} turned run {}", press.user.name, if state.run { "on" } else { "off" });
+            }
+            "stop" => {
+                // Owners stop everyone's actions, everyone else only their own
+                let is_owner = ctx.data().config.lock().unwrap().is_owner(press.user.id.get());
+                let cancelled = ctx.data().scheduler.cancel_user(if is_owner { None } else { Some(press.user.id) });
+                state.last = format!("{} stopped {} action(s)", press.user.name, cancelled);
+            }
+            suffix => {
+                if let Some((_, _, input)) = INPUT_BUTTONS.iter().find(|(button, _, _)| *button == suffix) {
+                    let mut action = Action {
+                        duration: state.duration,
+                        movement: None,
+                        look: None,
+                        run: state.run.then_some(true),
+                        jump: None,
+                    };
+                    input.apply(&mut action);
+                    let submitter = Submitter {
+                        user_id: press.user.id,
+                        user_name: press.user.name.clone(),
+                        role_ids: press.member.as_ref()
+                            .map(|member| member.roles.iter().map(|role_id| role_id.to_string()).collect())
+                            .unwrap_or_default(),
+                        channel_id: press.channel_id,
+                        guild_id: press.guild_id,
+                        source: "controller",
+                    };
+                    let description = describe_action(&action);
+                    let outcome = dispatch::submit(ctx.data(), &submitter, action);
+                    state.last = match outcome {
+                        Outcome::Queued(_) | Outcome::Voted(_) => format!("{} pressed {}: {}", press.user.name, description, outcome),
+                        _ => format!("{} couldn't press {}: {}", press.user.name, description, outcome),
+                    };
+                }
+            }
+        }
+
+        let response = press.create_response(ctx, CreateInteractionResponse::UpdateMessage(
+            CreateInteractionResponseMessage::new()
+                .embed(embed(ctx, &state))
+                .components(components(id, &state, &durations, true))
+        )).await;
+        if let Err(e) = response {
+            error!("Failed to answer a press on controller {}: {}", id, e);
+        }
+    }
+
+    // Disable the components once presses are no longer handled
+    state.last = "Controller timed out, use /controller for a new one".to_owned();
+    let edit = EditMessage::new()
+        .embed(embed(ctx, &state))
+        .components(components(id, &state, &durations, false));
+    if let Err(e) = message.edit(ctx, edit).await {
+        error!("Failed to disable controller {}: {}", id, e);
+    }
+    Ok(())
+}
@@ -0,0 +1,32 @@
+use poise::serenity_prelude::{CreateEmbed, Color, Timestamp, ChannelId};
+use poise::{ChoiceParameter, CreateReply};
+use crate::democracy::Mode;
+use crate::{Context, Error};
+
+/// Switches between anarchy (immediate actions) and democracy (voted actions)
+#[poise::command(prefix_command, slash_command, check = "crate::permissions::owners_only")]
+pub async fn mode(
+    ctx: Context<'_>,
+    #[description = "Mode to switch to"] mode: Mode,
+) -> Result<(), Error> {
+    // The tally goes to the configured channel, or wherever democracy was turned on
+    let channel = ctx.data().config.lock().unwrap().democracy.channel.parse::<u64>().ok()
+        .map_or(ctx.channel_id(), ChannelId::new);
+    ctx.data().democracy.set_mode(mode, channel);
+
+    let description = match mode {
+        Mode::Anarchy => "Actions are performed as soon as they are sent".to_owned(),
+        Mode::Democracy => format!("Actions are votes, the winner of each window is performed\nTally channel: <#{}>", channel),
+    };
+
+    let reply_embed = CreateEmbed::default()
+        .title(format!("Switched to {}", mode.name()))
+        .description(description)
+        .field("**Caller**", format!("{} ({})", ctx.author().name, ctx.author().id), false)
+        .color(Color::DARK_GREEN)
+        .thumbnail(ctx.author().face())
+        .timestamp(Timestamp::now());
+
+    ctx.send(CreateReply::default().embed(reply_embed)).await?;
+    Ok(())
+}
@@ -0,0 +1,32 @@
+use poise::serenity_prelude::{CreateEmbed, Color, ReactionType, Timestamp};
+use poise::CreateReply;
+use crate::{Context, Error};
+
+/// Posts a control panel, react to perform an action
+#[poise::command(prefix_command, slash_command)]
+pub async fn panel(ctx: Context<'_>) -> Result<(), Error> {
+    let (buttons, duration) = {
+        let config = ctx.data().config.lock().unwrap();
+        (config.panel.buttons.clone(), config.panel.duration)
+    };
+
+    let legend = buttons.iter()
+        .map(|button| format!("{} {:?}", button.emoji, button.input))
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    let reply_embed = CreateEmbed::default()
+        .title("Control panel")
+        .description(format!("React to perform an action for {}s, your reaction is removed so you can press it again.", duration))
+        .field("**Buttons**", legend, false)
+        .color(Color::BLURPLE)
+        .timestamp(Timestamp::now());
+
+    let message = ctx.send(CreateReply::default().embed(reply_embed)).await?.into_message().await?;
+    ctx.data().panels.add(message.id);
+
+    for button in buttons {
+        message.react(ctx, ReactionType::Unicode(button.emoji)).await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,64 @@
+use poise::serenity_prelude::{CreateEmbed, Color, Timestamp};
+use poise::CreateReply;
+use crate::{Context, Error};
+use crate::commands::vrc::describe_action;
+use crate::scheduler::{Lane, QueuedAction};
+
+// Longest list shown in the queue embed
+const MAX_LISTED: usize = 10;
+
+fn format_queued(queued: &QueuedAction) -> String {
+    let lane = match queued.lane {
+        Lane::Priority => " [priority]",
+        Lane::Normal => "",
+    };
+    format!("{} -> {} for {}s{}", queued.user_name, describe_action(&queued.action), queued.action.duration, lane)
+}
+
+/// Shows the actions waiting to be performed and where yours are
+#[poise::command(prefix_command, slash_command)]
+pub async fn queue(ctx: Context<'_>) -> Result<(), Error> {
+    let snapshot = ctx.data().scheduler.snapshot();
+
+    let running = match &snapshot.running {
+        Some(queued) => format_queued(queued),
+        None => "Nothing".to_owned(),
+    };
+
+    let waiting = if snapshot.waiting.is_empty() {
+        "Queue is empty".to_owned()
+    } else {
+        let mut lines: Vec<String> = snapshot.waiting.iter()
+            .take(MAX_LISTED)
+            .enumerate()
+            .map(|(index, queued)| format!("{}. {}", index + 1, format_queued(queued)))
+            .collect();
+        if snapshot.waiting.len() > MAX_LISTED {
+            lines.push(format!("...and {} more", snapshot.waiting.len() - MAX_LISTED));
+        }
+        lines.join("\n")
+    };
+
+    let positions: Vec<String> = snapshot.waiting.iter()
+        .enumerate()
+        .filter(|(_, queued)| queued.user_id == ctx.author().id)
+        .map(|(index, _)| (index + 1).to_string())
+        .collect();
+    let positions = if positions.is_empty() {
+        "You have no actions waiting".to_owned()
+    } else {
+        format!("Position(s): {}", positions.join(", "))
+    };
+
+    let reply_embed = CreateEmbed::default()
+        .title(format!("Action queue ({} waiting)", snapshot.waiting.len()))
+        .field("**Running**", running, false)
+        .field("**Waiting**", waiting, false)
+        .field("**Your actions**", positions, false)
+        .color(Color::BLURPLE)
+        .thumbnail(ctx.author().face())
+        .timestamp(Timestamp::now());
+
+    ctx.send(CreateReply::default().embed(reply_embed)).await?;
+    Ok(())
+}
@@ -0,0 +1,128 @@
+use std::time::{Duration, SystemTime};
+
+use poise::serenity_prelude::{CreateEmbed, Color, Timestamp};
+use poise::CreateReply;
+use crate::chatbox::moderation;
+use crate::timers;
+use crate::{Context, Error};
+
+// Longest a timer can run, anything later is most likely a typo
+const MAX_LENGTH: Duration = Duration::from_secs(7 * 24 * 3600);
+
+fn reply_embed(ctx: Context<'_>, title: &str, detail: String, color: Color) -> CreateEmbed {
+    CreateEmbed::default()
+        .title(title.to_owned())
+        .field("**Caller**", format!("{} ({})\n{}", ctx.author().name, ctx.author().id, detail), false)
+        .color(color)
+        .thumbnail(ctx.author().face())
+        .timestamp(Timestamp::now())
+}
+
+/// Starts a countdown to `ends` shown in the chatbox, the caller is pinged here once it ends
+async fn start(ctx: Context<'_>, ends: SystemTime, label: Option<String>) -> Result<(), Error> {
+    let label = label.map(|label| label.trim().to_owned()).filter(|label| !label.is_empty()).unwrap_or("Timer".to_owned());
+    let length = ends.duration_since(SystemTime::now()).unwrap_or_default();
+    if length.is_zero() || length > MAX_LENGTH {
+        let embed = reply_embed(ctx, "Invalid time", "Timers have to end in the future and within a week.".to_owned(), Color::RED);
+        ctx.send(CreateReply::default().ephemeral(true).embed(embed)).await?;
+        return Ok(());
+    }
+
+    // The label is shown in the chatbox for as long as the timer runs
+    let (filtered, max_timers) = {
+        let config = ctx.data().config.lock().unwrap();
+        (moderation::filter(&label, &config.moderation), config.timers.max_timers)
+    };
+    let label = match filtered {
+        Ok(label) => label,
+        Err(rejection) => {
+            println!("{} ({}) -> Timer label rejected: {}", ctx.author().name, ctx.author().id, rejection);
+            moderation::report(ctx.http(), &ctx.data().config, ctx.author(), &label, &rejection).await;
+            let embed = reply_embed(ctx, "Label rejected", format!("Reason: {}", rejection), Color::RED);
+            ctx.send(CreateReply::default().ephemeral(true).embed(embed)).await?;
+            return Ok(());
+        }
+    };
+
+    let Some(timer) = ctx.data().timers.start(label, ends, ctx.author().id, ctx.channel_id(), max_timers) else {
+        let embed = reply_embed(ctx, "Too many timers", format!("{} timers are already running, wait for one to end.", max_timers), Color::RED);
+        ctx.send(CreateReply::default().ephemeral(true).embed(embed)).await?;
+        return Ok(());
+    };
+
+    let detail = format!("#{} {} ends <t:{}:R>, you'll be pinged here", timer.id, timer.label, timers::unix_seconds(timer.ends));
+    ctx.send(CreateReply::default().embed(reply_embed(ctx, "Timer started", detail, Color::DARK_GREEN))).await?;
+    Ok(())
+}
+
+/// Counts down for a while in the chatbox, e.g. 5m or 1h30m
+#[poise::command(prefix_command, slash_command)]
+pub async fn timer(
+    ctx: Context<'_>,
+    #[description = "How long to count down, e.g. 90s, 5m or 1h30m"]
+    duration: String,
+    #[description = "What the timer is for"]
+    label: Option<String>,
+) -> Result<(), Error> {
+    let Some(duration) = timers::parse_duration(&duration) else {
+        let embed = reply_embed(ctx, "Invalid duration", format!("`{}` isn't a duration, try 90s, 5m or 1h30m.", duration), Color::RED);
+        ctx.send(CreateReply::default().ephemeral(true).embed(embed)).await?;
+        return Ok(());
+    };
+    let Some(ends) = SystemTime::now().checked_add(duration).filter(|_| duration <= MAX_LENGTH) else {
+        let embed = reply_embed(ctx, "Invalid duration", "Timers can run for at most a week.".to_owned(), Color::RED);
+        ctx.send(CreateReply::default().ephemeral(true).embed(embed)).await?;
+        return Ok(());
+    };
+    start(ctx, ends, label).await
+}
+
+/// Counts down to a point in time in the chatbox
+#[poise::command(prefix_command, slash_command)]
+pub async fn countdown(
+    ctx: Context<'_>,
+    #[description = "When the countdown ends: HH:MM, YYYY-MM-DD HH:MM, a Unix or Discord timestamp"]
+    timestamp: String,
+    #[description = "What the countdown is for"]
+    label: Option<String>,
+) -> Result<(), Error> {
+    let Some(ends) = timers::parse_timestamp(&timestamp) else {
+        let embed = reply_embed(ctx, "Invalid timestamp", format!("`{}` isn't a time, try 18:30, 2024-12-31 23:59 or a Discord timestamp.", timestamp), Color::RED);
+        ctx.send(CreateReply::default().ephemeral(true).embed(embed)).await?;
+        return Ok(());
+    };
+    start(ctx, ends, label).await
+}
+
+/// Stops your running timers and countdowns, or a single one by its number
+#[poise::command(prefix_command, slash_command)]
+pub async fn canceltimer(
+    ctx: Context<'_>,
+    #[description = "Number of the timer to stop, all of yours when left out"]
+    id: Option<u64>,
+) -> Result<(), Error> {
+    // Owners may stop anyone's timer by its number
+    let is_owner = ctx.data().config.lock().unwrap().is_owner(ctx.author().id.get());
+    let author = ctx.author().id;
+    let cancelled = ctx.data().timers.cancel_where(|timer| match id {
+        Some(id) => timer.id == id && (is_owner || timer.user == author),
+        None => timer.user == author,
+    });
+
+    if cancelled.is_empty() {
+        let detail = match id {
+            Some(id) => format!("Timer #{} isn't running or isn't yours.", id),
+            None => "You have no running timers.".to_owned(),
+        };
+        let embed = reply_embed(ctx, "No timer cancelled", detail, Color::RED);
+        ctx.send(CreateReply::default().ephemeral(true).embed(embed)).await?;
+        return Ok(());
+    }
+
+    let detail = cancelled.iter()
+        .map(|timer| format!("#{} {}", timer.id, timer.label))
+        .collect::<Vec<String>>()
+        .join("\n");
+    ctx.send(CreateReply::default().embed(reply_embed(ctx, "Timer cancelled", detail, Color::ORANGE))).await?;
+    Ok(())
+}
@@ -1,35 +1,167 @@
-use poise::serenity_prelude::{CreateEmbed, Color, Timestamp};
+use std::time::{Duration, UNIX_EPOCH};
+
+use log::error;
+use poise::serenity_prelude::{self as serenity, ButtonStyle, CreateActionRow, CreateButton, CreateEmbed, CreateInteractionResponse, CreateInteractionResponseMessage, Color, EditMessage, Timestamp, RoleId};
 use poise::CreateReply;
 use crate::{Context, Error};
+use crate::democracy::Mode;
+use crate::dispatch;
+use crate::democracy::vote::Choice;
+use crate::scheduler::{ActionStatus, Lane};
+use crate::utils;
 use crate::vrc_client;
 
+/// Owners and members of the configured priority role are served first
+async fn action_lane(ctx: Context<'_>) -> Lane {
+    let (is_owner, priority_role) = {
+        let config = ctx.data().config.lock().unwrap();
+        (config.is_owner(ctx.author().id.get()), config.queue.priority_role.clone())
+    };
+
+    if is_owner {
+        return Lane::Priority;
+    }
+
+    if let Ok(role_id) = priority_role.parse::<u64>() {
+        if let Some(member) = ctx.author_member().await {
+            if member.roles.contains(&RoleId::new(role_id)) {
+                return Lane::Priority;
+            }
+        }
+    }
+
+    Lane::Normal
+}
+
+/// Formats the inputs an action holds, e.g. "Forward, Left, run"
+pub fn describe_action(action: &vrc_client::Action) -> String {
+    let mut action_type: Vec<&str> = Vec::new();
+    if let Some(movement) = &action.movement {
+        action_type.push(movement);
+    }
+    if let Some(look) = &action.look {
+        action_type.push(look);
+    }
+    if action.jump.is_some() {
+        action_type.push("jump");
+    }
+    if action.run.is_some() {
+        action_type.push("run");
+    }
+    action_type.join(", ")
+}
+
+// How often the queue position is refreshed while an action waits
+const POSITION_REFRESH: Duration = Duration::from_secs(5);
+
+/// Embed following a queued action through its lifecycle
+fn status_embed(ctx: Context<'_>, action: &vrc_client::Action, status: &ActionStatus, position: usize, note: &str) -> CreateEmbed {
+    let (title, state, color) = match status {
+        ActionStatus::Queued => ("Action queued", format!("Queue position: {}", position), Color::GOLD),
+        ActionStatus::Running(release) => {
+            let release_unix = release.duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs());
+            ("Action running", format!("Releases <t:{}:R>", release_unix), Color::BLUE)
+        }
+        ActionStatus::Completed => ("Action completed", "Inputs released".to_owned(), Color::DARK_GREEN),
+        ActionStatus::Cancelled => ("Action cancelled", note.to_owned(), Color::DARK_GREY),
+    };
+
+    CreateEmbed::default()
+        .title(title)
+        .field("**Caller**", format!("{} ({})\nAction type: {}\nAction duration: {}\n{}", ctx.author().name, ctx.author().id, describe_action(action), action.duration, state), false)
+        .color(color)
+        .thumbnail(ctx.author().face())
+        .timestamp(Timestamp::now())
+}
+
+fn cancel_components(id: u64, status: &ActionStatus) -> Vec<CreateActionRow> {
+    if matches!(status, ActionStatus::Completed | ActionStatus::Cancelled) {
+        return Vec::new();
+    }
+    vec![CreateActionRow::Buttons(vec![
+        CreateButton::new(format!("{}:cancel", id)).label("Cancel").style(ButtonStyle::Danger),
+    ])]
+}
+
 /// Helper function for all actions
 async fn send_action(ctx: Context<'_>, action: vrc_client::Action) -> Result<(), Error> {
     let mut reply_embed = CreateEmbed::default();
 
-    match ctx.data().vrc_transmitter.send(action.clone()).await {
-        Ok(_) => {
-            // Format type of action performed
-            // TODO: Make this not buns
-            let mut action_type: String = String::from("");
-            if action.clone().movement != None {
-                action_type += action.clone().movement.unwrap().as_str();
-            }
-            if action.clone().look != None {
-                action_type += action.clone().look.unwrap().as_str();
-            }
-            if action.clone().jump != None {
-                action_type += "jump";
-            }
-            if action.clone().run != None {
-                action_type += "run"
-            }
+    // Owners aren't subject to cooldowns or quotas, usage is only recorded once the action or vote is accepted
+    let command = ctx.command().name.as_str();
+    if let Err(hit) = dispatch::check_limits(ctx.data(), ctx.author().id, ctx.guild_id(), command) {
+        reply_embed = reply_embed
+            .title("Slow down!")
+            .field("**Caller**", format!("{} ({})\nReason: {}\nWait: {}", ctx.author().name, ctx.author().id, hit, utils::format_duration(hit.wait)), false)
+            .color(Color::ORANGE)
+            .thumbnail(ctx.author().face())
+            .timestamp(Timestamp::now());
+        ctx.send(CreateReply::default().ephemeral(true).embed(reply_embed)).await?;
+        return Ok(());
+    }
+
+    // In democracy mode actions are votes for the current window
+    if ctx.data().democracy.mode() == Mode::Democracy {
+        let Some(choice) = Choice::from_action(&action) else {
             reply_embed = reply_embed
-                .title("Successfully sent action")
-                .field("**Caller**", format!("{} ({})\nAction type: {}\nAction duration: {}", ctx.author().name, ctx.author().id, action_type, action.duration), false)
-                .color(Color::DARK_GREEN)
+                .title("Nothing to vote for")
+                .field("**Caller**", format!("{} ({})\nThe action doesn't move, look, run or jump.", ctx.author().name, ctx.author().id), false)
+                .color(Color::RED)
                 .thumbnail(ctx.author().face())
                 .timestamp(Timestamp::now());
+            ctx.send(CreateReply::default().ephemeral(true).embed(reply_embed)).await?;
+            return Ok(());
+        };
+        let changed = ctx.data().democracy.cast(ctx.author().id, choice.clone());
+        dispatch::record_limits(ctx.data(), ctx.author().id, ctx.guild_id(), command);
+        reply_embed = reply_embed
+            .title(if changed { "Vote changed" } else { "Vote counted" })
+            .field("**Caller**", format!("{} ({})\nVoted for: {}", ctx.author().name, ctx.author().id, choice), false)
+            .color(Color::BLURPLE)
+            .thumbnail(ctx.author().face())
+            .timestamp(Timestamp::now());
+        ctx.send(CreateReply::default().ephemeral(true).embed(reply_embed)).await?;
+        return Ok(());
+    }
+
+    // Each action holds the queue for its whole duration, so long ones are refused for everyone
+    let max_duration = ctx.data().config.lock().unwrap().queue.max_duration;
+    if action.duration > max_duration {
+        reply_embed = reply_embed
+            .title("Action too long")
+            .field("**Caller**", format!("{} ({})\nActions can last at most {} seconds.", ctx.author().name, ctx.author().id, max_duration), false)
+            .color(Color::RED)
+            .thumbnail(ctx.author().face())
+            .timestamp(Timestamp::now());
+        ctx.send(CreateReply::default().ephemeral(true).embed(reply_embed)).await?;
+        return Ok(());
+    }
+
+    // Only the current driver may move while someone has taken control, owners can always override
+    let is_owner = ctx.data().config.lock().unwrap().is_owner(ctx.author().id.get());
+    let may_drive = if is_owner { Ok(()) } else { ctx.data().control.may_drive(ctx.author().id) };
+    if let Err(driver) = may_drive {
+        let hint = match ctx.data().control.rotation() {
+            Some(_) => "Use /takecontrol to join the waitlist.",
+            None => "Wait for their turn to end.",
+        };
+        reply_embed = reply_embed
+            .title("Someone else has control")
+            .field("**Caller**", format!("{} ({})\n{}\n{}", ctx.author().name, ctx.author().id, driver, hint), false)
+            .color(Color::RED)
+            .thumbnail(ctx.author().face())
+            .timestamp(Timestamp::now());
+        ctx.send(CreateReply::default().ephemeral(true).embed(reply_embed)).await?;
+        return Ok(());
+    }
+
+    let lane = action_lane(ctx).await;
+    let max_pending_per_user = ctx.data().config.lock().unwrap().queue.max_pending_per_user;
+
+    let mut ticket = match ctx.data().scheduler.push(ctx.author().id, ctx.author().name.clone(), lane, action.clone(), max_pending_per_user) {
+        Ok(ticket) => {
+            dispatch::record_limits(ctx.data(), ctx.author().id, ctx.guild_id(), command);
+            ticket
         }
         Err(e) => {
             reply_embed = reply_embed
@@ -38,10 +170,72 @@ async fn send_action(ctx: Context<'_>, action: vrc_client::Action) -> Result<(),
                 .color(Color::RED)
                 .thumbnail(ctx.author().face())
                 .timestamp(Timestamp::now());
+            ctx.send(CreateReply::default().embed(reply_embed)).await?;
+            return Ok(());
         }
     };
 
-    ctx.send(CreateReply::default().embed(reply_embed)).await?;
+    // Follow the action until it completes or is cancelled, editing the reply whenever it changes
+    let id = ctx.id();
+    let mut note = String::new();
+    let mut shown = ticket.status.borrow_and_update().clone();
+    let mut position = ticket.position;
+    let reply = ctx.send(CreateReply::default()
+        .embed(status_embed(ctx, &action, &shown, position, &note))
+        .components(cancel_components(id, &shown))
+    ).await?;
+    // Actions can wait longer than the interaction token lasts, the message is edited with the bot token instead
+    let mut message = reply.message().await?.into_owned();
+
+    while !matches!(shown, ActionStatus::Completed | ActionStatus::Cancelled) {
+        tokio::select! {
+            // The final status is always sent before the sender is dropped, so an error needs no handling
+            _ = ticket.status.changed() => {}
+            press = serenity::ComponentInteractionCollector::new(ctx)
+                .filter(move |press| press.data.custom_id == format!("{}:cancel", id))
+                .timeout(POSITION_REFRESH) => {
+                if let Some(press) = press {
+                    // The caller may cancel their own action, owners may cancel anyone's
+                    let is_owner = ctx.data().config.lock().unwrap().is_owner(press.user.id.get());
+                    if press.user.id == ctx.author().id || is_owner {
+                        if ctx.data().scheduler.cancel(ticket.id) {
+                            note = format!("Cancelled by {}", press.user.name);
+                            println!("{} ({}) -> cancelled action {} of {}", press.user.name, press.user.id, ticket.id, ctx.author().name);
+                        }
+                        if let Err(e) = press.create_response(ctx, CreateInteractionResponse::Acknowledge).await {
+                            error!("Failed to acknowledge cancelling action {}: {}", ticket.id, e);
+                        }
+                    } else {
+                        let response = press.create_response(ctx, CreateInteractionResponse::Message(
+                            CreateInteractionResponseMessage::new()
+                                .content(format!("Only {} or an owner can cancel this action", ctx.author().name))
+                                .ephemeral(true)
+                        )).await;
+                        if let Err(e) = response {
+                            error!("Failed to refuse cancelling action {}: {}", ticket.id, e);
+                        }
+                    }
+                }
+            }
+        }
+
+        let status = ticket.status.borrow_and_update().clone();
+        let new_position = ctx.data().scheduler.position(ticket.id).unwrap_or(position);
+        if status == shown && new_position == position {
+            continue;
+        }
+        shown = status;
+        position = new_position;
+        if shown == ActionStatus::Cancelled && note.is_empty() {
+            note = "Cancelled from another command".to_owned();
+        }
+        let edit = EditMessage::new()
+            .embed(status_embed(ctx, &action, &shown, position, &note))
+            .components(cancel_components(id, &shown));
+        if let Err(e) = message.edit(ctx, edit).await {
+            error!("Failed to update the status of action {}: {}", ticket.id, e);
+        }
+    }
     Ok(())
 }
 
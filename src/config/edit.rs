@@ -0,0 +1,205 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+
+use toml_edit::{DocumentMut, Item, Table};
+
+use super::{parse_value, Config, OVERRIDE_PREFIX};
+use crate::utils;
+
+// Fields never shown or changed from Discord, the token file is included so the token can't be redirected
+const SECRET_FIELDS: [&str; 2] = ["auth.token", "auth.token_file"];
+
+// Paths which can't be changed from Discord, pointing them at the config or token file would show or overwrite it
+// Every `*.state_file` is included as well
+const PROTECTED_PATHS: [&str; 1] = ["file_source.path"];
+
+fn is_secret(field: &str) -> bool {
+    SECRET_FIELDS.contains(&field)
+}
+
+fn is_protected(field: &str) -> bool {
+    is_secret(field) || PROTECTED_PATHS.contains(&field) || field.ends_with(".state_file")
+}
+
+/// Name of the environment variable overriding a field, e.g. DVO_CHATBOX__INTERVAL_SECS
+pub fn override_variable(field: &str) -> String {
+    format!("{}{}", OVERRIDE_PREFIX, field.replace('.', "__").to_uppercase())
+}
+
+/// Whether an environment variable overrides what the config file says for a field
+pub fn is_overridden(field: &str) -> bool {
+    env::var_os(override_variable(field)).is_some()
+}
+
+impl Config {
+    /// Current value of a field or section such as `chatbox.interval_secs`, secrets are left out
+    pub fn get(&self, field: &str) -> Result<toml::Value, String> {
+        if is_secret(field) {
+            return Err(format!("{} is secret", field));
+        }
+        let mut value = toml::Value::try_from(self).map_err(|e| e.to_string())?;
+        for secret in SECRET_FIELDS {
+            if let Some((section, key)) = secret.split_once('.') {
+                if let Some(section) = value.get_mut(section).and_then(toml::Value::as_table_mut) {
+                    section.remove(key);
+                }
+            }
+        }
+
+        for key in field.split('.') {
+            value = match value {
+                toml::Value::Table(mut table) => table.remove(key),
+                _ => None,
+            }.ok_or_else(|| format!("{} isn't a config field", field))?;
+        }
+        Ok(value)
+    }
+}
+
+/// Changes a field of the shared config and saves it to the config file at `path`
+/// `value` is given the current config and returns the new value as text, like in an environment override
+/// The config stays locked until the change is saved and swapped in, so changes and reloads can't interleave
+/// Returns the field's old value, if it had one, along with the new value
+pub fn update(
+    config: &Mutex<Config>,
+    path: &Path,
+    field: &str,
+    value: impl FnOnce(&Config) -> Result<String, String>,
+) -> Result<(Option<toml::Value>, toml::Value), String> {
+    let mut config = config.lock().unwrap();
+    let root = toml::Value::try_from(&*config).map_err(|e| e.to_string())?;
+    let old = config.get(field).ok();
+    let value = value(&config)?;
+    let (updated, new) = set_in(root, path, field, &value)?;
+    *config = updated;
+    Ok((old, new))
+}
+
+/// Sets a field of the shared config to `value` and saves it, see `update`
+pub fn set(config: &Mutex<Config>, path: &Path, field: &str, value: &str) -> Result<(Option<toml::Value>, toml::Value), String> {
+    update(config, path, field, |_| Ok(value.to_owned()))
+}
+
+/// Sets a field in `root`, the serialized config, and writes it to the config file at `path`
+/// The change is checked against the schema and validated first, the file keeps its comments and formatting
+/// Returns the updated config for the caller to swap in, along with the value that was set
+fn set_in(mut root: toml::Value, path: &Path, field: &str, value: &str) -> Result<(Config, toml::Value), String> {
+    if is_protected(field) {
+        return Err(format!("{} can't be changed from Discord", field));
+    }
+    let keys: Vec<&str> = field.split('.').collect();
+    let Some((last, sections)) = keys.split_last().filter(|_| keys.iter().all(|key| !key.is_empty())) else {
+        return Err(format!("{} isn't a config field", field));
+    };
+
+    // Only existing sections can be entered, so typos are caught instead of creating new ones
+    let mut table = root.as_table_mut().ok_or("The config isn't a table")?;
+    for section in sections {
+        table = table.get_mut(*section)
+            .and_then(toml::Value::as_table_mut)
+            .ok_or_else(|| format!("{} isn't a config field", field))?;
+    }
+    if let Some(toml::Value::Table(_)) = table.get(*last) {
+        return Err(format!("{} is a section, set its fields one at a time", field));
+    }
+    let value = parse_value(table.get(*last), value);
+    table.insert((*last).to_owned(), value.clone());
+
+    let mut config: Config = root.try_into().map_err(|e| format!("Invalid value for {}: {}", field, e))?;
+    // Fields the schema doesn't know are dropped while parsing, so they are missing once read back
+    if config.get(field).ok().as_ref() != Some(&value) {
+        return Err(format!("{} isn't a config field", field));
+    }
+    let mut errors = config.validate();
+    if let Err(e) = config.moderation.compile() {
+        errors.push(e);
+    }
+    if !errors.is_empty() {
+        return Err(errors.join("\n"));
+    }
+
+    write_field(path, &keys, &value)?;
+    Ok((config, value))
+}
+
+/// Replaces one value in the config file, keeping everything else as the user wrote it
+fn write_field(path: &Path, keys: &[&str], value: &toml::Value) -> Result<(), String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let mut document: DocumentMut = text.parse().map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+    let mut new: toml_edit::Value = value.to_string().parse().map_err(|e| format!("Failed to write {}: {}", value, e))?;
+
+    let Some((last, sections)) = keys.split_last() else {
+        return Ok(());
+    };
+    let mut table = document.as_table_mut() as &mut dyn toml_edit::TableLike;
+    for section in sections {
+        // Sections missing from the file are added without an empty header for their parents
+        let mut missing = Table::new();
+        missing.set_implicit(true);
+        table = table.entry(section)
+            .or_insert(Item::Table(missing))
+            .as_table_like_mut()
+            .ok_or_else(|| format!("{} in {} isn't a section", section, path.display()))?;
+    }
+
+    // The new value takes over the old one's surrounding whitespace and trailing comment
+    match table.get_mut(last) {
+        Some(Item::Value(existing)) => {
+            *new.decor_mut() = existing.decor().clone();
+            *existing = new;
+        }
+        Some(item) => *item = Item::Value(new),
+        None => {
+            table.insert(last, Item::Value(new));
+        }
+    }
+
+    utils::write_atomic(path, &document.to_string()).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> Config {
+        toml::from_str(r#"
+            [auth]
+            token = "MTIzNDU2Nzg5MDEyMzQ1Njc4.GhIjKl.secret-part-of-the-token"
+            owners = ["239561212818489344"]
+
+            [options]
+            prefixes = ["!"]
+            mention_as_prefix = true
+
+            [system]
+            ephemeral_admin_commands = true
+            vrc_client_logging_channel = ""
+
+            [vrc_client]
+            localhost = "127.0.0.1"
+            receiver_port = 9001
+            transmitter_port = 9000
+        "#).unwrap()
+    }
+
+    #[test]
+    fn set_saves_the_field_and_keeps_comments() {
+        let path = env::temp_dir().join(format!("discord-to-vrc-osc-set-{}.toml", std::process::id()));
+        fs::write(&path, "[chatbox]\ninterval_secs = 10 # How often\n").unwrap();
+        let config = Mutex::new(config());
+
+        let (old, new) = update(&config, &path, "chatbox.templates", |_| Ok(r#"["a", "b"]"#.to_owned())).unwrap();
+        let refused = ["auth.token_file", "file_source.path", "limits.state_file"].map(|field| set(&config, &path, field, "config.toml"));
+        let written = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(old, Some(toml::Value::Array(Vec::new())));
+        assert_eq!(new.to_string(), r#"["a", "b"]"#);
+        assert_eq!(config.lock().unwrap().chatbox.templates, vec!["a".to_owned(), "b".to_owned()]);
+        assert!(written.contains("interval_secs = 10 # How often"));
+        assert!(written.contains(r#"templates = ["a", "b"]"#));
+        assert!(refused.iter().all(Result::is_err));
+    }
+}
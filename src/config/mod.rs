@@ -1,37 +1,71 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env;
 use std::fs;
-use std::fmt::Write;
-use toml;
+use std::fmt;
+use std::path::{Path, PathBuf};
 
+use crate::permissions::Capability;
+use crate::chatbox::{moderation, widgets};
+use crate::text_control::TextInput;
 use crate::utils;
 
+pub mod edit;
+pub mod reload;
+pub mod secret;
+
+pub use secret::Secret;
+
+// Looked for in the working directory when no path is given, Windows accepts / as a separator too
 const CONFIG_FILEPATHS: [&str ; 4] = [
     "config.toml",
     "Config.toml",
-    "config\\config.toml",
-    "config\\Config.toml"
+    "config/config.toml",
+    "config/Config.toml"
 ];
 
-#[derive(Deserialize, Serialize)]
+// Directory under the XDG config dir, e.g. ~/.config/discord-to-vrc-osc/config.toml
+const CONFIG_DIR: &str = "discord-to-vrc-osc";
+
+// Environment variable naming the config file, overridden by --config
+const CONFIG_ENV: &str = "DVO_CONFIG";
+
+// systemd credential the token is read from when the config doesn't hold it, see LoadCredential=
+const TOKEN_CREDENTIAL: &str = "discord_token";
+
+// Environment variables starting with this override config fields, e.g. DVO_AUTH__TOKEN sets auth.token
+const OVERRIDE_PREFIX: &str = "DVO_";
+
+// Longest anyone may hold control for, a week
+const MAX_CONTROL_MINUTES: u64 = 7 * 24 * 60;
+
+// Longest a single action or voting window may last, a day
+const MAX_ACTION_SECS: u64 = 24 * 60 * 60;
+
+#[derive(Default, Deserialize, Serialize)]
 pub struct Auth {
-    pub token: String,
+    #[serde(default)]
+    pub token: Secret, // Also read from DVO_AUTH__TOKEN, `token_file` or the discord_token systemd credential
+    #[serde(default)]
+    pub token_file: String, // File holding the token, used when `token` is empty
     pub owners: Vec<String>,
 }
 
-#[derive(Deserialize, Serialize)]
+#[derive(Default, Deserialize, Serialize)]
 pub struct Options {
     pub prefixes: Vec<String>,
     pub mention_as_prefix: bool,
-    pub message: String, // Implemented in some test variations
+    #[serde(default)]
+    pub message: String, // Status line used when `chatbox.templates` is empty
 }
 
-#[derive(Deserialize, Serialize)]
+#[derive(Default, Deserialize, Serialize)]
 pub struct System {
     pub ephemeral_admin_commands: bool,
     pub vrc_client_logging_channel: String,
 }
 
-#[derive(Deserialize, Serialize)]
+#[derive(Default, Deserialize, Serialize)]
 pub struct VrcClient {
     pub localhost: String,
     pub receiver_port: u16,
@@ -39,53 +73,959 @@ pub struct VrcClient {
 }
 
 #[derive(Deserialize, Serialize)]
+#[serde(default)]
+pub struct Queue {
+    pub max_pending_per_user: usize, // Actions a single user may have waiting at once
+    pub priority_role: String, // Role ID whose members share the owners' priority lane
+    pub max_duration: u64, // Longest action in seconds, each one holds the queue for its whole duration
+}
+
+impl Default for Queue {
+    fn default() -> Self {
+        Queue {
+            max_pending_per_user: 3,
+            priority_role: String::new(),
+            max_duration: 30,
+        }
+    }
+}
+
+/// A set of limits, each disabled when left at 0
+#[derive(Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LimitRule {
+    pub cooldown: u64,    // Seconds between uses
+    pub rate_limit: u32,  // Uses allowed per `rate_window`
+    pub rate_window: u64, // Seconds
+    pub daily_quota: u32, // Uses allowed per UTC day
+}
+
+impl LimitRule {
+    /// Whether any of the limits is set, disabled rules don't track usage
+    pub fn is_enabled(&self) -> bool {
+        self.cooldown > 0 || (self.rate_limit > 0 && self.rate_window > 0) || self.daily_quota > 0
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Limits {
+    pub state_file: String, // Where usage is saved so limits survive restarts
+    pub user: LimitRule,    // Applies to each user
+    pub guild: LimitRule,   // Applies to each guild as a whole
+    pub commands: HashMap<String, LimitRule>, // Applies to each user per command name
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            state_file: "limits.toml".to_owned(),
+            user: LimitRule::default(),
+            guild: LimitRule::default(),
+            commands: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(default)]
+pub struct Access {
+    pub state_file: String, // Where the channel allowlist and user blocklist are saved
+}
+
+impl Default for Access {
+    fn default() -> Self {
+        Access {
+            state_file: "access.toml".to_owned(),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(default)]
+pub struct Control {
+    pub max_minutes: u64,  // Longest a user may take control for at once
+    pub turn_minutes: u64, // Default turn length in rotation mode
+    pub banner: bool,      // Whether to name the current driver in the chatbox
+}
+
+impl Default for Control {
+    fn default() -> Self {
+        Control {
+            max_minutes: 30,
+            turn_minutes: 5,
+            banner: true,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(default)]
+pub struct Democracy {
+    pub window_secs: u64,     // Length of each voting window
+    pub action_duration: u64, // Seconds the winning action is performed for
+    pub channel: String,      // Channel ID for the live tally, defaults to where /mode was used
+}
+
+impl Default for Democracy {
+    fn default() -> Self {
+        Democracy {
+            window_secs: 15,
+            action_duration: 2,
+            channel: String::new(),
+        }
+    }
+}
+
+/// Bare messages in `channel` become actions, e.g. "w", "a 3" or "run left"
+#[derive(Deserialize, Serialize)]
+#[serde(default)]
+pub struct TextControl {
+    pub channel: String,      // Channel ID, text control is off while empty
+    pub duration: u64,        // Seconds an action lasts when no duration is given
+    pub max_duration: u64,    // Longest duration a message may ask for
+    pub throttle_secs: u64,   // Seconds between actions from the same user
+    pub keywords: HashMap<String, TextInput>, // Built-in keywords are used while empty
+}
+
+impl Default for TextControl {
+    fn default() -> Self {
+        TextControl {
+            channel: String::new(),
+            duration: 1,
+            max_duration: 10,
+            throttle_secs: 2,
+            keywords: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct PanelButton {
+    pub emoji: String,
+    pub input: TextInput,
+}
+
+/// Reactions on /panel messages and the inputs they perform
+#[derive(Deserialize, Serialize)]
+#[serde(default)]
+pub struct Panel {
+    pub duration: u64, // Seconds each press lasts
+    pub throttle_secs: u64, // Seconds between presses from the same user
+    pub state_file: String, // Where posted panels are saved so they keep working after a restart
+    pub buttons: Vec<PanelButton>, // In the order they are added to the panel
+}
+
+impl Default for Panel {
+    fn default() -> Self {
+        let buttons = [
+            ("⬆️", TextInput::Forward),
+            ("⬇️", TextInput::Backward),
+            ("⬅️", TextInput::Left),
+            ("➡️", TextInput::Right),
+            ("🔄", TextInput::LookRight),
+            ("🦘", TextInput::Jump),
+        ];
+        Panel {
+            duration: 2,
+            throttle_secs: 2,
+            state_file: "panels.toml".to_owned(),
+            buttons: buttons.into_iter()
+                .map(|(emoji, input)| PanelButton { emoji: emoji.to_owned(), input })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(default)]
+pub struct Controller {
+    pub durations: Vec<u64>,  // Seconds offered by the duration menu, the first is selected initially
+    pub timeout_minutes: u64, // Presses are handled for this long after /controller is used
+}
+
+impl Default for Controller {
+    fn default() -> Self {
+        Controller {
+            durations: vec![1, 2, 3, 5, 10],
+            timeout_minutes: 60,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(default)]
+pub struct Chatbox {
+    pub enabled: bool, // Whether status templates are shown
+    pub interval_secs: u64, // How often the next template is shown
+    pub templates: Vec<String>, // Status lines rotated through, `options.message` is used when empty
+    pub page_dwell_secs: u64, // How long each page of a long message stays up before the next one
+    pub typing_indicator: bool, // Show the typing indicator while the next page is pending
+    pub max_pages: usize, // Longer messages are refused
+    pub min_interval_ms: u64, // Minimum time between chatbox updates, VRChat drops updates sent faster
+}
+
+impl Default for Chatbox {
+    fn default() -> Self {
+        Chatbox {
+            enabled: true,
+            interval_secs: 10,
+            templates: Vec::new(),
+            page_dwell_secs: 5,
+            typing_indicator: true,
+            max_pages: 5,
+            min_interval_ms: 1500,
+        }
+    }
+}
+
+/// A built-in chatbox widget, shown in the status rotation while enabled
+#[derive(Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Widget {
+    pub enabled: bool,
+    pub template: String, // Same placeholders as chatbox.templates, the widget's default when empty
+}
+
+/// Track playing in an MPRIS player on the D-Bus session bus, Linux only
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct NowPlayingWidget {
+    pub enabled: bool,
+    pub template: String, // Only shown while a track is playing or paused
+    pub player: String, // Bus name suffix such as "spotify", the first player found when empty
+    pub poll_secs: u64,
+    pub bar_width: usize, // Characters in the {progress} bar
+}
+
+impl Default for NowPlayingWidget {
+    fn default() -> Self {
+        NowPlayingWidget {
+            enabled: false,
+            template: widgets::template_or_default("now_playing", "").to_owned(),
+            player: String::new(),
+            poll_secs: 2,
+            bar_width: 10,
+        }
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Widgets {
+    pub time_format: String, // strftime format of {time}
+    pub clock: Widget,
+    pub uptime: Widget,
+    pub system: Widget, // CPU and RAM usage, read from /proc on Linux
+    pub actions: Widget,
+    pub queue: Widget,
+    pub now_playing: NowPlayingWidget,
+}
+
+impl Widgets {
+    /// Enabled widgets by name, in rotation order
+    pub fn enabled(&self) -> Vec<(&'static str, &Widget)> {
+        [
+            ("clock", &self.clock),
+            ("uptime", &self.uptime),
+            ("system", &self.system),
+            ("actions", &self.actions),
+            ("queue", &self.queue),
+        ]
+            .into_iter()
+            .filter(|(_, widget)| widget.enabled)
+            .collect()
+    }
+}
+
+impl Default for Widgets {
+    fn default() -> Self {
+        let widget = |name: &str| Widget {
+            enabled: false,
+            template: widgets::template_or_default(name, "").to_owned(),
+        };
+        Widgets {
+            time_format: "%H:%M".to_owned(),
+            clock: widget("clock"),
+            uptime: widget("uptime"),
+            system: widget("system"),
+            actions: widget("actions"),
+            queue: widget("queue"),
+            now_playing: NowPlayingWidget::default(),
+        }
+    }
+}
+
+/// Text file shown in the chatbox whenever it changes, such as an OBS text source or song log
+#[derive(Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct FileSource {
+    pub enabled: bool,
+    pub path: String,
+    pub repeat_secs: u64, // Show the content again this often, 0 to only show it when it changes
+}
+
+/// Filter applied to everything shown in the chatbox
+#[derive(Deserialize, Serialize)]
+#[serde(default)]
+pub struct Moderation {
+    pub enabled: bool,
+    pub blocklist: Vec<String>, // Words and phrases which reject a message, matched after normalizing lookalike characters
+    pub patterns: Vec<String>, // Regular expressions which reject a message
+    pub strip_links: bool, // Replace links with [link]
+    pub max_repeat: usize, // Longer runs of the same character are shortened, 0 to disable
+    #[serde(skip)]
+    pub rules: moderation::Rules, // Built from the fields above by compile
+}
+
+impl Default for Moderation {
+    fn default() -> Self {
+        Moderation {
+            enabled: true,
+            blocklist: Vec::new(),
+            patterns: Vec::new(),
+            strip_links: true,
+            max_repeat: 4,
+            rules: moderation::Rules::default(),
+        }
+    }
+}
+
+impl Moderation {
+    /// Compiles the blocklist and patterns for the filter, done whenever a config is loaded or changed
+    /// This is also what checks the patterns, so each one is compiled only once
+    pub fn compile(&mut self) -> Result<(), String> {
+        self.rules = moderation::Rules::new(self).map_err(|e| {
+            // The set doesn't say which pattern is invalid, they are only tried one by one to find it once compiling failed
+            match self.patterns.iter().position(|pattern| regex::Regex::new(pattern).is_err()) {
+                Some(index) => format!("moderation.patterns[{}]: invalid regular expression: {}", index, e),
+                None => format!("moderation: {}", e),
+            }
+        })?;
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(default)]
+pub struct Bridge {
+    pub min_display_secs: u64, // How long each relayed message stays up at least
+    pub prefixes: HashMap<String, String>, // Discord user ID to text put before their name
+}
+
+impl Default for Bridge {
+    fn default() -> Self {
+        Bridge {
+            min_display_secs: 3,
+            prefixes: HashMap::new(),
+        }
+    }
+}
+
+/// Timers and countdowns shown in the chatbox
+#[derive(Deserialize, Serialize)]
+#[serde(default)]
+pub struct Timers {
+    pub refresh_secs: u64, // How often the countdown in the chatbox is updated
+    pub max_timers: usize, // How many timers may run at once
+}
+
+impl Default for Timers {
+    fn default() -> Self {
+        Timers {
+            refresh_secs: 2,
+            max_timers: 5,
+        }
+    }
+}
+
+/// Capabilities granted by Discord user, role and channel IDs, on top of `default`
+#[derive(Deserialize, Serialize)]
+#[serde(default)]
+pub struct Permissions {
+    pub default: Vec<Capability>, // Granted to everyone
+    pub users: HashMap<String, Vec<Capability>>,
+    pub roles: HashMap<String, Vec<Capability>>,
+    pub channels: HashMap<String, Vec<Capability>>, // Granted to anyone using commands in the channel
+}
+
+impl Default for Permissions {
+    fn default() -> Self {
+        Permissions {
+            default: vec![Capability::Movement],
+            users: HashMap::new(),
+            roles: HashMap::new(),
+            channels: HashMap::new(),
+        }
+    }
+}
+
+// Default is only used as the schema for environment overrides, auth, options, system and vrc_client are required in the file
+#[derive(Default, Deserialize, Serialize)]
 pub struct Config {
     pub auth: Auth,
     pub options: Options,
     pub system: System,
     pub vrc_client: VrcClient,
+    #[serde(default)]
+    pub queue: Queue,
+    #[serde(default)]
+    pub limits: Limits,
+    #[serde(default)]
+    pub permissions: Permissions,
+    #[serde(default)]
+    pub access: Access,
+    #[serde(default)]
+    pub control: Control,
+    #[serde(default)]
+    pub democracy: Democracy,
+    #[serde(default)]
+    pub text_control: TextControl,
+    #[serde(default)]
+    pub panel: Panel,
+    #[serde(default)]
+    pub controller: Controller,
+    #[serde(default)]
+    pub chatbox: Chatbox,
+    #[serde(default)]
+    pub widgets: Widgets,
+    #[serde(default)]
+    pub file_source: FileSource,
+    #[serde(default)]
+    pub moderation: Moderation,
+    #[serde(default)]
+    pub bridge: Bridge,
+    #[serde(default)]
+    pub timers: Timers,
 }
 
 impl Config {
-    pub fn new() -> Self {
-        for path in CONFIG_FILEPATHS {
-            if let Ok(toml_string) = fs::read_to_string(path) {
-                match toml::from_str(&toml_string) {
-                    Ok(config) => return config,
-                    Err(e) => panic!("Failed to parse {}: {}", path, e),
+    /// Finds, reads and validates the config file, the error lists everything wrong with it
+    pub fn new() -> Result<Self, String> {
+        let path = Self::path().ok_or_else(|| format!("No config file found, pass one with --config or {}", CONFIG_ENV))?;
+        Self::load(&path)
+    }
+
+    /// The config file to use: --config, then DVO_CONFIG, then the working directory, then the XDG config dir
+    /// A path given on the command line or in the environment is used even if it doesn't exist, so the error names it
+    pub fn path() -> Option<PathBuf> {
+        if let Some(path) = cli_path() {
+            return Some(path);
+        }
+        if let Some(path) = env::var_os(CONFIG_ENV).filter(|path| !path.is_empty()) {
+            return Some(PathBuf::from(path));
+        }
+
+        let xdg = env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()).map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+            .or_else(|| env::var_os("APPDATA").map(PathBuf::from))
+            .map(|dir| dir.join(CONFIG_DIR).join("config.toml"));
+        CONFIG_FILEPATHS.iter()
+            .map(PathBuf::from)
+            .chain(xdg)
+            .find(|path| path.is_file())
+    }
+
+    /// Reads, parses and validates the config file at `path`, with environment overrides applied
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let toml_string = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let mut table: toml::Table = toml::from_str(&toml_string).map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+        // Fields missing from the file take their type from the defaults, so IDs stay text
+        let schema = toml::Value::try_from(Config::default()).ok();
+        for (name, value) in env::vars_os() {
+            // Unrelated variables may hold anything, only overrides have to be valid text
+            let lossy = name.to_string_lossy();
+            if !lossy.starts_with(OVERRIDE_PREFIX) || name == CONFIG_ENV {
+                continue;
+            }
+            let (Some(name), Some(value)) = (name.to_str(), value.to_str()) else {
+                return Err(format!("{} isn't valid UTF-8", lossy));
+            };
+            apply_override(&mut table, schema.as_ref(), &name[OVERRIDE_PREFIX.len()..], value)?;
+        }
+        let raw = toml::Value::Table(table.clone());
+        let mut config: Config = match table.try_into() {
+            Ok(config) => config,
+            Err(e) => {
+                // Parsing stops at the first wrong type, each field is tried on its own so every one is reported
+                let defaults = schema.as_ref().and_then(toml::Value::as_table);
+                let mut errors = defaults.map(|defaults| type_errors(raw.as_table().unwrap_or(defaults), defaults)).unwrap_or_default();
+                if errors.is_empty() {
+                    errors.push(e.to_string());
                 }
+                return Err(format!("Invalid {}:\n{}", path.display(), errors.iter().map(|error| format!("  {}", error)).collect::<Vec<String>>().join("\n")));
+            }
+        };
+
+        let mut errors = Vec::new();
+        if let Err(e) = resolve_token(&mut config.auth) {
+            errors.push(e);
+        }
+        // Keys the schema doesn't know are dropped while parsing, so they are missing once serialized again
+        if let Ok(parsed) = toml::Value::try_from(&config) {
+            unknown_keys("", &raw, &parsed, &mut errors);
+        }
+        errors.extend(config.validate());
+        if let Err(e) = config.moderation.compile() {
+            errors.push(e);
+        }
+        if !errors.is_empty() {
+            return Err(format!("Invalid {}:\n{}", path.display(), errors.iter().map(|error| format!("  {}", error)).collect::<Vec<String>>().join("\n")));
+        }
+        Ok(config)
+    }
+
+    /// Checks values which parse fine but would break the bot once used, as "field.path: problem"
+    /// Moderation patterns are left to `Moderation::compile`, which checks them while compiling
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        if self.auth.token.is_empty() {
+            errors.push(format!("auth.token: is empty, and neither auth.token_file nor the {} credential holds a token", TOKEN_CREDENTIAL));
+        }
+        for (index, owner) in self.auth.owners.iter().enumerate() {
+            check_id(&format!("auth.owners[{}]", index), owner, &mut errors);
+        }
+        check_id("system.vrc_client_logging_channel", &self.system.vrc_client_logging_channel, &mut errors);
+        check_id("queue.priority_role", &self.queue.priority_role, &mut errors);
+        check_id("democracy.channel", &self.democracy.channel, &mut errors);
+        check_id("text_control.channel", &self.text_control.channel, &mut errors);
+        for (section, ids) in [("users", &self.permissions.users), ("roles", &self.permissions.roles), ("channels", &self.permissions.channels)] {
+            for id in ids.keys() {
+                check_id(&format!("permissions.{}.{}", section, id), id, &mut errors);
+            }
+        }
+        for id in self.bridge.prefixes.keys() {
+            check_id(&format!("bridge.prefixes.{}", id), id, &mut errors);
+        }
+
+        for (field, minutes) in [("control.max_minutes", self.control.max_minutes), ("control.turn_minutes", self.control.turn_minutes)] {
+            if minutes > MAX_CONTROL_MINUTES {
+                errors.push(format!("{}: must be at most {} (a week)", field, MAX_CONTROL_MINUTES));
+            }
+        }
+
+        for (field, seconds) in [("queue.max_duration", self.queue.max_duration), ("democracy.window_secs", self.democracy.window_secs)] {
+            if seconds > MAX_ACTION_SECS {
+                errors.push(format!("{}: must be at most {} (a day)", field, MAX_ACTION_SECS));
             }
         }
-        panic!("Failed to parse config")
+
+        // Discord select menus need between 1 and 25 options
+        if self.controller.durations.is_empty() {
+            errors.push("controller.durations: is empty, the duration menu needs at least one option".to_owned());
+        } else if self.controller.durations.len() > 25 {
+            errors.push(format!("controller.durations: has {} entries, Discord allows at most 25", self.controller.durations.len()));
+        }
+        if self.controller.timeout_minutes > MAX_CONTROL_MINUTES {
+            errors.push(format!("controller.timeout_minutes: must be at most {} (a week)", MAX_CONTROL_MINUTES));
+        }
+
+        // Both ports bound by one client would fight over the same socket
+        if self.vrc_client.receiver_port == self.vrc_client.transmitter_port {
+            errors.push(format!("vrc_client.transmitter_port: is the same as vrc_client.receiver_port ({})", self.vrc_client.receiver_port));
+        }
+        errors
     }
 
-    pub fn update(&mut self) {
-        *self = Self::new();
+    /// Fields which differ from `other` as "section.field: old → new", the token is never shown
+    pub fn diff(&self, other: &Config) -> Vec<String> {
+        let (Ok(old), Ok(new)) = (toml::Value::try_from(self), toml::Value::try_from(other)) else {
+            return Vec::new();
+        };
+        let mut changes = Vec::new();
+        diff_values("", &old, &new, &mut changes);
+        changes
     }
 
     //pub fn set_logging_channel(&mut self, channel: ChannelId) {
     //    self.system.logging.log_channel = channel.to_string();
     //}
 
-    pub fn to_string(&self) -> String {
-        let mut output = String::new();
+    /// Whether the given Discord user ID is listed in `auth.owners`
+    pub fn is_owner(&self, user_id: u64) -> bool {
+        self.auth.owners.iter().any(|owner| owner.parse::<u64>() == Ok(user_id))
+    }
+
+    pub fn print(&self) {
+        println!("{}", self);
+    }
+}
+
+/// The path after --config or --config=, if given
+fn cli_path() -> Option<PathBuf> {
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--config" || arg == "-c" {
+            return args.next().map(PathBuf::from);
+        }
+        if let Some(path) = arg.strip_prefix("--config=") {
+            return Some(PathBuf::from(path));
+        }
+    }
+    None
+}
+
+/// Reads a value given as text as TOML, e.g. 9001 or ["a", "b"]
+/// It is kept as plain text when it isn't valid TOML or replaces text, so IDs and words need no quotes
+fn parse_value(existing: Option<&toml::Value>, value: &str) -> toml::Value {
+    let parsed = match existing {
+        Some(toml::Value::String(_)) => None,
+        _ => toml::from_str::<toml::Table>(&format!("value = {}", value)).ok().and_then(|mut parsed| parsed.remove("value")),
+    };
+    parsed.unwrap_or_else(|| toml::Value::String(value.to_owned()))
+}
+
+/// Sets the field named by an environment variable such as AUTH__TOKEN, sections are separated by __
+/// `schema` is the default config, whose fields decide how values for fields missing from `table` are read
+fn apply_override(table: &mut toml::Table, schema: Option<&toml::Value>, field: &str, value: &str) -> Result<(), String> {
+    let keys: Vec<String> = field.split("__").map(str::to_lowercase).collect();
+    let Some((last, sections)) = keys.split_last() else {
+        return Ok(());
+    };
+
+    let mut current = table;
+    let mut schema = schema.and_then(toml::Value::as_table);
+    for section in sections {
+        let entry = current.entry(section.clone()).or_insert_with(|| toml::Value::Table(toml::Table::new()));
+        current = entry.as_table_mut()
+            .ok_or_else(|| format!("{}{} overrides {}, which isn't a section", OVERRIDE_PREFIX, field, section))?;
+        schema = schema.and_then(|schema| schema.get(section)).and_then(toml::Value::as_table);
+    }
+
+    let existing = current.get(last).or_else(|| schema.and_then(|schema| schema.get(last)));
+    let value = parse_value(existing, value);
+    current.insert(last.clone(), value);
+    Ok(())
+}
+
+/// Type errors of every field in `table`, found by parsing each one alone on top of the defaults
+fn type_errors(table: &toml::Table, defaults: &toml::Table) -> Vec<String> {
+    let mut fields = Vec::new();
+    for (section, value) in table {
+        match (value, defaults.get(section)) {
+            (toml::Value::Table(values), Some(toml::Value::Table(_))) => {
+                fields.extend(values.iter().map(|(field, value)| (section.as_str(), Some(field.as_str()), value)));
+            }
+            _ => fields.push((section.as_str(), None, value)),
+        }
+    }
+
+    let mut errors = Vec::new();
+    for (section, field, value) in fields {
+        let mut config = defaults.clone();
+        let name = match field {
+            Some(field) => {
+                if let Some(toml::Value::Table(values)) = config.get_mut(section) {
+                    values.insert(field.to_owned(), value.clone());
+                }
+                format!("{}.{}", section, field)
+            }
+            None => {
+                config.insert(section.to_owned(), value.clone());
+                section.to_owned()
+            }
+        };
+        if let Err(e) = config.try_into::<Config>() {
+            // The message ends with the field's path, which the name already gives
+            errors.push(format!("{}: {}", name, e.to_string().lines().next().unwrap_or_default()));
+        }
+    }
+    errors
+}
+
+/// Fills in the token from `auth.token_file`, or else the systemd credential, when the config doesn't hold it
+fn resolve_token(auth: &mut Auth) -> Result<(), String> {
+    if !auth.token.is_empty() {
+        return Ok(());
+    }
+    let path = if !auth.token_file.is_empty() {
+        PathBuf::from(&auth.token_file)
+    } else if let Some(directory) = env::var_os("CREDENTIALS_DIRECTORY") {
+        let path = Path::new(&directory).join(TOKEN_CREDENTIAL);
+        if !path.is_file() {
+            return Ok(());
+        }
+        path
+    } else {
+        return Ok(());
+    };
+
+    let source = if auth.token_file.is_empty() { format!("{} credential", TOKEN_CREDENTIAL) } else { "auth.token_file".to_owned() };
+    let token = fs::read_to_string(&path).map_err(|e| format!("{}: failed to read {}: {}", source, path.display(), e))?;
+    auth.token = Secret::new(token.trim().to_owned());
+    Ok(())
+}
 
-        writeln!(output, "Configuration:").unwrap();
+/// Adds an error unless `value` is empty or a Discord ID
+fn check_id(field: &str, value: &str, errors: &mut Vec<String>) {
+    if !value.is_empty() && value.parse::<u64>().is_err() {
+        errors.push(format!("{}: \"{}\" isn't a Discord ID", field, value));
+    }
+}
+
+/// Collects keys in the file which are missing from the parsed config, descending into tables and arrays
+fn unknown_keys(path: &str, raw: &toml::Value, parsed: &toml::Value, errors: &mut Vec<String>) {
+    match (raw, parsed) {
+        (toml::Value::Table(raw), toml::Value::Table(parsed)) => {
+            for (key, value) in raw {
+                let field = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+                match parsed.get(key) {
+                    Some(parsed) => unknown_keys(&field, value, parsed, errors),
+                    None => errors.push(format!("{}: unknown key", field)),
+                }
+            }
+        }
+        (toml::Value::Array(raw), toml::Value::Array(parsed)) => {
+            for (index, (raw, parsed)) in raw.iter().zip(parsed).enumerate() {
+                unknown_keys(&format!("{}[{}]", path, index), raw, parsed, errors);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Collects differences between two TOML values, descending into tables
+fn diff_values(path: &str, old: &toml::Value, new: &toml::Value, changes: &mut Vec<String>) {
+    if let (toml::Value::Table(old), toml::Value::Table(new)) = (old, new) {
+        let mut keys: Vec<&String> = old.keys().chain(new.keys()).collect();
+        keys.sort();
+        keys.dedup();
+        for key in keys {
+            let field = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+            match (old.get(key), new.get(key)) {
+                (Some(old), Some(new)) => diff_values(&field, old, new, changes),
+                (Some(old), None) => changes.push(format!("{}: {} → (removed)", field, old)),
+                (None, Some(new)) => changes.push(format!("{}: (added) → {}", field, new)),
+                (None, None) => {}
+            }
+        }
+    } else if old != new {
+        if path == "auth.token" {
+            changes.push(format!("{}: (changed)", path));
+        } else {
+            changes.push(format!("{}: {} → {}", path, old, new));
+        }
+    }
+}
+
+// Debug output goes through Display so the token stays hidden there too
+impl fmt::Debug for Config {
+    fn fmt(&self, output: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, output)
+    }
+}
+
+impl fmt::Display for Config {
+    fn fmt(&self, output: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(output, "Configuration:")?;
 
         // Auth section
-        writeln!(output, "{}", utils::format_section("Auth")).unwrap();
-        writeln!(output, "{}", utils::format_field("Token", &format!("{}...", self.auth.token.chars().take(10).collect::<String>()))).unwrap();
-        writeln!(output, "{}", utils::format_list("Owners", &self.auth.owners)).unwrap();
+        writeln!(output, "{}", utils::format_section("Auth"))?;
+        writeln!(output, "{}", utils::format_field("Token", &self.auth.token.to_string()))?;
+        writeln!(output, "{}", utils::format_field("Token File", &self.auth.token_file))?;
+        writeln!(output, "{}", utils::format_list("Owners", &self.auth.owners))?;
         // Options section
-        writeln!(output, "{}", utils::format_section("Options")).unwrap();
-        writeln!(output, "{}", utils::format_field("Ephemeral Admin Commands", &self.system.ephemeral_admin_commands.to_string())).unwrap();
-        writeln!(output, "{}", utils::format_list("Prefixes", &self.options.prefixes)).unwrap();
-        writeln!(output, "{}", utils::format_field("Mention as Prefix", &self.options.mention_as_prefix.to_string())).unwrap();
+        writeln!(output, "{}", utils::format_section("Options"))?;
+        writeln!(output, "{}", utils::format_field("Ephemeral Admin Commands", &self.system.ephemeral_admin_commands.to_string()))?;
+        writeln!(output, "{}", utils::format_list("Prefixes", &self.options.prefixes))?;
+        writeln!(output, "{}", utils::format_field("Mention as Prefix", &self.options.mention_as_prefix.to_string()))?;
+        // Queue section
+        writeln!(output, "{}", utils::format_section("Queue"))?;
+        writeln!(output, "{}", utils::format_field("Max Pending per User", &self.queue.max_pending_per_user.to_string()))?;
+        writeln!(output, "{}", utils::format_field("Priority Role", &self.queue.priority_role))?;
+        writeln!(output, "{}", utils::format_field("Max Duration", &self.queue.max_duration.to_string()))?;
+        // Limits section
+        writeln!(output, "{}", utils::format_section("Limits"))?;
+        writeln!(output, "{}", utils::format_field("State File", &self.limits.state_file))?;
+        writeln!(output, "{}", utils::format_list("Limited Commands", &self.limits.commands.keys().cloned().collect::<Vec<String>>()))?;
+        // Permissions section
+        writeln!(output, "{}", utils::format_section("Permissions"))?;
+        writeln!(output, "{}", utils::format_list("Default", &self.permissions.default.iter().map(Capability::to_string).collect::<Vec<String>>()))?;
+        writeln!(output, "{}", utils::format_field("Access State File", &self.access.state_file))?;
+        writeln!(output, "{}", utils::format_field("Users / Roles / Channels", &format!("{} / {} / {}", self.permissions.users.len(), self.permissions.roles.len(), self.permissions.channels.len())))?;
+        // Control section
+        writeln!(output, "{}", utils::format_section("Control"))?;
+        writeln!(output, "{}", utils::format_field("Max Minutes", &self.control.max_minutes.to_string()))?;
+        writeln!(output, "{}", utils::format_field("Turn Minutes", &self.control.turn_minutes.to_string()))?;
+        writeln!(output, "{}", utils::format_field("Banner", &self.control.banner.to_string()))?;
+        // Democracy section
+        writeln!(output, "{}", utils::format_section("Democracy"))?;
+        writeln!(output, "{}", utils::format_field("Window Seconds", &self.democracy.window_secs.to_string()))?;
+        writeln!(output, "{}", utils::format_field("Action Duration", &self.democracy.action_duration.to_string()))?;
+        writeln!(output, "{}", utils::format_field("Channel", &self.democracy.channel))?;
+        // Text control section
+        writeln!(output, "{}", utils::format_section("Text Control"))?;
+        writeln!(output, "{}", utils::format_field("Channel", &self.text_control.channel))?;
+        writeln!(output, "{}", utils::format_field("Throttle Seconds", &self.text_control.throttle_secs.to_string()))?;
+        writeln!(output, "{}", utils::format_field("Custom Keywords", &self.text_control.keywords.len().to_string()))?;
+        // Panel section
+        writeln!(output, "{}", utils::format_section("Panel"))?;
+        writeln!(output, "{}", utils::format_field("Duration", &self.panel.duration.to_string()))?;
+        writeln!(output, "{}", utils::format_field("Throttle Secs", &self.panel.throttle_secs.to_string()))?;
+        writeln!(output, "{}", utils::format_field("State File", &self.panel.state_file))?;
+        writeln!(output, "{}", utils::format_list("Buttons", &self.panel.buttons.iter().map(|button| format!("{} {:?}", button.emoji, button.input)).collect::<Vec<String>>()))?;
+        // Controller section
+        writeln!(output, "{}", utils::format_section("Controller"))?;
+        writeln!(output, "{}", utils::format_list("Durations", &self.controller.durations.iter().map(u64::to_string).collect::<Vec<String>>()))?;
+        writeln!(output, "{}", utils::format_field("Timeout Minutes", &self.controller.timeout_minutes.to_string()))?;
+        // Chatbox section
+        writeln!(output, "{}", utils::format_section("Chatbox"))?;
+        writeln!(output, "{}", utils::format_field("Enabled", &self.chatbox.enabled.to_string()))?;
+        writeln!(output, "{}", utils::format_field("Interval Seconds", &self.chatbox.interval_secs.to_string()))?;
+        writeln!(output, "{}", utils::format_list("Templates", &self.chatbox.templates))?;
+        writeln!(output, "{}", utils::format_field("Page Dwell Seconds", &self.chatbox.page_dwell_secs.to_string()))?;
+        writeln!(output, "{}", utils::format_field("Typing Indicator", &self.chatbox.typing_indicator.to_string()))?;
+        writeln!(output, "{}", utils::format_field("Max Pages", &self.chatbox.max_pages.to_string()))?;
+        writeln!(output, "{}", utils::format_field("Min Interval ms", &self.chatbox.min_interval_ms.to_string()))?;
+        // Widgets section
+        writeln!(output, "{}", utils::format_section("Widgets"))?;
+        writeln!(output, "{}", utils::format_field("Time Format", &self.widgets.time_format))?;
+        writeln!(output, "{}", utils::format_list("Enabled", &self.widgets.enabled().iter().map(|(name, _)| (*name).to_owned()).collect::<Vec<String>>()))?;
+        writeln!(output, "{}", utils::format_field("Now Playing", &self.widgets.now_playing.enabled.to_string()))?;
+        // File Source section
+        writeln!(output, "{}", utils::format_section("File Source"))?;
+        writeln!(output, "{}", utils::format_field("Enabled", &self.file_source.enabled.to_string()))?;
+        writeln!(output, "{}", utils::format_field("Path", &self.file_source.path))?;
+        writeln!(output, "{}", utils::format_field("Repeat Seconds", &self.file_source.repeat_secs.to_string()))?;
+        // Moderation section
+        writeln!(output, "{}", utils::format_section("Moderation"))?;
+        writeln!(output, "{}", utils::format_field("Enabled", &self.moderation.enabled.to_string()))?;
+        writeln!(output, "{}", utils::format_field("Blocked Words", &self.moderation.blocklist.len().to_string()))?;
+        writeln!(output, "{}", utils::format_field("Blocked Patterns", &self.moderation.patterns.len().to_string()))?;
+        writeln!(output, "{}", utils::format_field("Strip Links", &self.moderation.strip_links.to_string()))?;
+        writeln!(output, "{}", utils::format_field("Max Repeat", &self.moderation.max_repeat.to_string()))?;
+        // Bridge section
+        writeln!(output, "{}", utils::format_section("Bridge"))?;
+        writeln!(output, "{}", utils::format_field("Min Display Seconds", &self.bridge.min_display_secs.to_string()))?;
+        writeln!(output, "{}", utils::format_list("Prefixed Users", &self.bridge.prefixes.keys().cloned().collect::<Vec<String>>()))?;
+        // Timers section
+        writeln!(output, "{}", utils::format_section("Timers"))?;
+        writeln!(output, "{}", utils::format_field("Refresh Seconds", &self.timers.refresh_secs.to_string()))?;
+        writeln!(output, "{}", utils::format_field("Max Timers", &self.timers.max_timers.to_string()))?;
 
-        output
+        Ok(())
     }
+}
 
-    pub fn print(&self) {
-        println!("{}", self.to_string());
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOKEN: &str = "MTIzNDU2Nzg5MDEyMzQ1Njc4.GhIjKl.secret-part-of-the-token";
+
+    fn config(auth: &str) -> Config {
+        let toml_string = format!(r#"
+            [auth]
+            {}
+            owners = ["239561212818489344"]
+
+            [options]
+            prefixes = ["!"]
+            mention_as_prefix = true
+
+            [system]
+            ephemeral_admin_commands = true
+            vrc_client_logging_channel = ""
+
+            [vrc_client]
+            localhost = "127.0.0.1"
+            receiver_port = 9001
+            transmitter_port = 9000
+        "#, auth);
+        toml::from_str(&toml_string).unwrap()
+    }
+
+    /// Fails if any part of the token of 6 or more characters shows up in `text`
+    fn assert_no_secret(text: &str) {
+        let chars: Vec<char> = TOKEN.chars().collect();
+        for window in chars.windows(6) {
+            let part: String = window.iter().collect();
+            assert!(!text.contains(&part), "{:?} from the token shows up in:\n{}", part, text);
+        }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn formatted_config_never_shows_the_token() {
+        let config = config(&format!("token = \"{}\"", TOKEN));
+
+        assert_no_secret(&config.to_string());
+        assert_no_secret(&format!("{:?}", config));
+        assert_no_secret(&format!("{:?}", config.auth.token));
+        assert_no_secret(&config.get("auth").unwrap().to_string());
+        assert!(config.get("auth.token").is_err());
+        assert!(config.to_string().contains("[redacted]"));
+    }
+
+    #[test]
+    fn changed_token_is_not_shown_in_diff() {
+        let old = config("token = \"old-token-value\"");
+        let new = config(&format!("token = \"{}\"", TOKEN));
+
+        let changes = old.diff(&new);
+        assert_eq!(changes, vec!["auth.token: (changed)".to_owned()]);
+        assert_no_secret(&changes.join("\n"));
+    }
+
+    #[test]
+    fn token_is_read_from_token_file() {
+        let path = env::temp_dir().join(format!("discord-to-vrc-osc-key-{}", std::process::id()));
+        fs::write(&path, format!("{}\n", TOKEN)).unwrap();
+        let mut config = config(&format!("token_file = {:?}", path.display().to_string()));
+
+        resolve_token(&mut config.auth).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(config.auth.token.expose(), TOKEN);
+        assert!(config.validate().is_empty());
+        assert_no_secret(&config.to_string());
+    }
+
+    #[test]
+    fn empty_controller_durations_are_rejected() {
+        let mut config = config(&format!("token = \"{}\"", TOKEN));
+        assert!(config.validate().is_empty());
+
+        config.controller.durations.clear();
+        assert!(config.validate().iter().any(|error| error.starts_with("controller.durations")));
+    }
+
+    #[test]
+    fn invalid_pattern_is_reported_by_index() {
+        let mut config = config(&format!("token = \"{}\"", TOKEN));
+        config.moderation.patterns = vec!["free nitro".to_owned(), "(unclosed".to_owned()];
+
+        let error = config.moderation.compile().unwrap_err();
+        assert!(error.starts_with("moderation.patterns[1]: invalid regular expression"));
+    }
+
+    #[test]
+    fn overrides_of_missing_fields_take_the_schema_type() {
+        let schema = toml::Value::try_from(Config::default()).unwrap();
+        let mut table = toml::Table::new();
+        apply_override(&mut table, Some(&schema), "DEMOCRACY__CHANNEL", "123456789012345678").unwrap();
+        apply_override(&mut table, Some(&schema), "QUEUE__PRIORITY_ROLE", "987654321098765432").unwrap();
+        apply_override(&mut table, Some(&schema), "QUEUE__MAX_DURATION", "10").unwrap();
+
+        assert_eq!(table["democracy"]["channel"], toml::Value::String("123456789012345678".to_owned()));
+        assert_eq!(table["queue"]["priority_role"], toml::Value::String("987654321098765432".to_owned()));
+        assert_eq!(table["queue"]["max_duration"], toml::Value::Integer(10));
+
+        // The same override on a whole config, where the democracy section is missing from the file
+        let mut config = toml::Table::try_from(config(&format!("token = \"{}\"", TOKEN))).unwrap();
+        config.remove("democracy");
+        apply_override(&mut config, Some(&schema), "DEMOCRACY__CHANNEL", "123456789012345678").unwrap();
+        let config: Config = config.try_into().unwrap();
+        assert_eq!(config.democracy.channel, "123456789012345678");
+    }
+
+    #[test]
+    fn every_wrong_type_is_reported() {
+        let path = env::temp_dir().join(format!("discord-to-vrc-osc-types-{}.toml", std::process::id()));
+        fs::write(&path, format!("[auth]\ntoken = \"{}\"\n[chatbox]\ninterval_secs = \"ten\"\n[queue]\nmax_duration = []\n", TOKEN)).unwrap();
+
+        let error = Config::load(&path).err().unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(error.contains("chatbox.interval_secs"));
+        assert!(error.contains("queue.max_duration"));
+        assert_no_secret(&error);
+    }
+}
@@ -0,0 +1,101 @@
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use log::{error, warn};
+use notify::{RecursiveMode, Watcher};
+use poise::serenity_prelude::{self as serenity, ChannelId, Color, CreateEmbed, CreateMessage, Timestamp};
+use tokio::sync::mpsc;
+
+use crate::utils;
+use super::Config;
+
+// Editors often save in several steps, changes within this window cause a single reload
+const SETTLE_TIME: Duration = Duration::from_millis(300);
+
+/// Posts a reload result to the logging channel, if one is configured
+async fn log(http: &serenity::Http, config: &Mutex<Config>, title: &str, text: &str, color: Color) {
+    let channel = config.lock().unwrap().system.vrc_client_logging_channel.parse::<u64>();
+    let Some(channel) = channel.ok().filter(|channel| *channel != 0) else {
+        return;
+    };
+
+    let embed = CreateEmbed::default()
+        .title(title.to_owned())
+        .description(utils::truncate(text, 4096)) // Discord limits descriptions to 4096 characters
+        .color(color)
+        .timestamp(Timestamp::now());
+
+    if let Err(e) = ChannelId::new(channel).send_message(http, CreateMessage::new().embed(embed)).await {
+        error!("Failed to post config reload to the logging channel: {}", e);
+    }
+}
+
+/// Reloads the config whenever its file changes, an invalid file is reported and the running config kept
+pub async fn run(http: Arc<serenity::Http>, config: Arc<Mutex<Config>>) {
+    let Some(path) = Config::path() else {
+        return;
+    };
+
+    let (sender, mut events) = mpsc::unbounded_channel();
+    let watcher = notify::recommended_watcher(move |event| {
+        let _ = sender.send(event);
+    });
+    let mut watcher = match watcher {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            error!("Failed to start the config watcher: {}", e);
+            return;
+        }
+    };
+    if let Err(e) = watcher.watch(&utils::watch_dir(&path), RecursiveMode::NonRecursive) {
+        error!("Failed to watch {}: {}", path.display(), e);
+        return;
+    }
+
+    while let Some(event) = events.recv().await {
+        let concerns_config = match event {
+            Ok(event) => (event.kind.is_modify() || event.kind.is_create())
+                && event.paths.iter().any(|changed| changed.file_name() == path.file_name()),
+            Err(e) => {
+                warn!("Config watcher error: {}", e);
+                false
+            }
+        };
+        if !concerns_config {
+            continue;
+        }
+        tokio::time::sleep(SETTLE_TIME).await;
+        while events.try_recv().is_ok() {}
+
+        reload(&http, &config, &path).await;
+    }
+}
+
+/// Swaps in the config file at `path` if it is valid, posting what changed
+async fn reload(http: &serenity::Http, config: &Mutex<Config>, path: &Path) {
+    let new = match Config::load(path) {
+        Ok(new) => new,
+        Err(e) => {
+            warn!("Keeping the current config: {}", e);
+            log(http, config, "Config reload failed, keeping the current config", &e, Color::RED).await;
+            return;
+        }
+    };
+
+    let changes = {
+        let mut config = config.lock().unwrap();
+        let changes = config.diff(&new);
+        if !changes.is_empty() {
+            *config = new;
+        }
+        changes
+    };
+    if changes.is_empty() {
+        return;
+    }
+
+    println!("Reloaded {}: {}", path.display(), changes.join(", "));
+    let text = changes.iter().map(|change| format!("`{}`", change)).collect::<Vec<String>>().join("\n");
+    log(http, config, "Config reloaded", &text, Color::DARK_GREEN).await;
+}
@@ -0,0 +1,166 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use poise::serenity_prelude::UserId;
+
+use crate::utils;
+
+#[derive(Clone, Debug)]
+pub struct Driver {
+    pub user_id: UserId,
+    pub user_name: String,
+    pub until: Instant,
+}
+
+impl Driver {
+    pub fn remaining(&self) -> Duration {
+        self.until.saturating_duration_since(Instant::now())
+    }
+}
+
+/// Result of asking for control
+pub enum TakeResult {
+    Granted(Duration),
+    Extended(Duration), // The caller already had control
+    Waitlisted(usize),  // 1-based position in the rotation waitlist
+    Refused(Driver),
+}
+
+#[derive(Default)]
+struct State {
+    driver: Option<Driver>,
+    rotation: Option<Duration>, // Turn length while rotation mode is on
+    waitlist: VecDeque<(UserId, String)>,
+}
+
+/// Exclusive controller lock for movement commands, with an optional handoff rotation
+#[derive(Default)]
+pub struct ControlLock {
+    state: Mutex<State>,
+}
+
+impl ControlLock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn driver(&self) -> Option<Driver> {
+        self.state.lock().unwrap().driver.clone()
+    }
+
+    pub fn rotation(&self) -> Option<Duration> {
+        self.state.lock().unwrap().rotation
+    }
+
+    pub fn waitlist(&self) -> Vec<String> {
+        self.state.lock().unwrap().waitlist.iter().map(|(_, user_name)| user_name.clone()).collect()
+    }
+
+    /// Whether the user may send movement commands right now
+    pub fn may_drive(&self, user_id: UserId) -> Result<(), Driver> {
+        match &self.state.lock().unwrap().driver {
+            Some(driver) if driver.user_id != user_id => Err(driver.clone()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Gives control to the caller if it is free, otherwise waitlists them while rotation mode is on
+    pub fn take(&self, user_id: UserId, user_name: String, duration: Duration) -> TakeResult {
+        let mut guard = self.state.lock().unwrap();
+        let state = &mut *guard;
+        // Turns are fixed length while rotating
+        let duration = state.rotation.unwrap_or(duration);
+
+        match &mut state.driver {
+            Some(driver) if driver.user_id == user_id => {
+                driver.until = Instant::now() + duration;
+                TakeResult::Extended(duration)
+            }
+            Some(driver) => {
+                let driver = driver.clone();
+                if state.rotation.is_none() {
+                    return TakeResult::Refused(driver);
+                }
+                if let Some(index) = state.waitlist.iter().position(|(waiting, _)| *waiting == user_id) {
+                    return TakeResult::Waitlisted(index + 1);
+                }
+                state.waitlist.push_back((user_id, user_name));
+                TakeResult::Waitlisted(state.waitlist.len())
+            }
+            None => {
+                state.driver = Some(Driver { user_id, user_name, until: Instant::now() + duration });
+                TakeResult::Granted(duration)
+            }
+        }
+    }
+
+    /// Owner override, replaces the current driver without touching the waitlist
+    pub fn force(&self, user_id: UserId, user_name: String, duration: Duration) {
+        let mut state = self.state.lock().unwrap();
+        state.waitlist.retain(|(waiting, _)| *waiting != user_id);
+        state.driver = Some(Driver { user_id, user_name, until: Instant::now() + duration });
+    }
+
+    /// Ends the current turn, handing off to the next waiting user in rotation mode
+    pub fn release(&self) -> Option<Driver> {
+        let mut state = self.state.lock().unwrap();
+        state.driver = None;
+        Self::hand_off(&mut state)
+    }
+
+    /// Leaves the waitlist, returns false if the user wasn't waiting
+    pub fn leave_waitlist(&self, user_id: UserId) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let length = state.waitlist.len();
+        state.waitlist.retain(|(waiting, _)| *waiting != user_id);
+        state.waitlist.len() != length
+    }
+
+    /// Turns rotation mode on with the given turn length, or off with None
+    pub fn set_rotation(&self, turn: Option<Duration>) {
+        let mut state = self.state.lock().unwrap();
+        state.rotation = turn;
+        if turn.is_none() {
+            state.waitlist.clear();
+        }
+    }
+
+    /// Expires the current turn if it is over, returns the new driver if control was handed off
+    pub fn tick(&self) -> Option<Driver> {
+        let mut state = self.state.lock().unwrap();
+        match &state.driver {
+            Some(driver) if driver.until <= Instant::now() => {
+                state.driver = None;
+                Self::hand_off(&mut state)
+            }
+            _ => None,
+        }
+    }
+
+    fn hand_off(state: &mut State) -> Option<Driver> {
+        let turn = state.rotation?;
+        let (user_id, user_name) = state.waitlist.pop_front()?;
+        let driver = Driver { user_id, user_name, until: Instant::now() + turn };
+        state.driver = Some(driver.clone());
+        Some(driver)
+    }
+
+    /// Chatbox line naming the current driver, None while nobody has control
+    pub fn banner(&self) -> Option<String> {
+        let state = self.state.lock().unwrap();
+        let driver = state.driver.as_ref()?;
+        let mut banner = format!("🎮 Driver: {} ({} left)", driver.user_name, utils::format_duration(driver.remaining().as_secs()));
+        if let Some((_, next)) = state.waitlist.front() {
+            banner.push_str(&format!(" | Next: {}", next));
+        }
+        Some(banner)
+    }
+}
+
+impl fmt::Display for Driver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} has control for {}", self.user_name, utils::format_duration(self.remaining().as_secs()))
+    }
+}
@@ -0,0 +1,228 @@
+pub mod vote;
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use log::error;
+use poise::serenity_prelude::{self as serenity, ChannelId, Color, CreateEmbed, CreateMessage, EditMessage, MessageId, ReactionType, Timestamp, UserId};
+
+use crate::config::Config;
+use crate::dispatch::{self, Submitter};
+use crate::scheduler::{Lane, Scheduler};
+use crate::Data;
+use vote::{Choice, Election, VOTE_EMOJIS};
+
+// How often the live tally is refreshed while votes are coming in
+const REFRESH_INTERVAL: Duration = Duration::from_secs(3);
+
+/// How actions sent through commands are handled
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum Mode {
+    #[default]
+    #[name = "anarchy"]
+    Anarchy, // Every action is queued immediately
+    #[name = "democracy"]
+    Democracy, // Actions are votes, the winner of each window is queued
+}
+
+#[derive(Default)]
+struct State {
+    mode: Mode,
+    channel: Option<ChannelId>, // Where the live tally is posted
+    election: Election,
+    message: Option<MessageId>, // The live tally of the current window
+    changed: bool,              // Whether the tally changed since it was last shown
+    switches: u64,              // Mode changes so far, a window that saw one is discarded
+}
+
+#[derive(Default)]
+pub struct Democracy {
+    state: Mutex<State>,
+}
+
+impl Democracy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.state.lock().unwrap().mode
+    }
+
+    /// Switches modes, the live tally is posted to `channel` while in democracy
+    /// Changing modes throws away the open window and its votes
+    pub fn set_mode(&self, mode: Mode, channel: ChannelId) {
+        let mut state = self.state.lock().unwrap();
+        if state.mode != mode {
+            state.switches += 1;
+            state.election = Election::default();
+            state.changed = true;
+        }
+        state.mode = mode;
+        state.channel = Some(channel);
+    }
+
+    /// Whether a message is the live tally of the open window
+    pub fn is_tally(&self, message_id: MessageId) -> bool {
+        self.state.lock().unwrap().message == Some(message_id)
+    }
+
+    /// Returns true if the voter changed an earlier vote
+    pub fn cast(&self, voter: UserId, choice: Choice) -> bool {
+        let mut state = self.state.lock().unwrap();
+        state.changed = true;
+        state.election.cast(voter.get(), choice)
+    }
+
+    /// Counts a reaction on the live tally as a vote, returns false if it isn't one
+    pub fn cast_reaction(&self, message_id: MessageId, voter: UserId, emoji: &ReactionType) -> bool {
+        let ReactionType::Unicode(emoji) = emoji else {
+            return false;
+        };
+        let Some(choice) = Choice::from_emoji(emoji) else {
+            return false;
+        };
+
+        let mut state = self.state.lock().unwrap();
+        if state.mode != Mode::Democracy || state.message != Some(message_id) {
+            return false;
+        }
+        state.changed = true;
+        state.election.cast(voter.get(), choice);
+        true
+    }
+}
+
+/// Counts a reaction on the live tally as a vote, if the voter passes the same checks as movement commands
+pub async fn handle_reaction(ctx: &serenity::Context, data: &Data, reaction: &serenity::Reaction) {
+    if !data.democracy.is_tally(reaction.message_id) {
+        return;
+    }
+    let Some(submitter) = Submitter::from_reaction(reaction, "vote") else {
+        return;
+    };
+    if submitter.user_id == ctx.cache.current_user().id {
+        return;
+    }
+
+    if let Err(outcome) = dispatch::admit(data, &submitter) {
+        println!("{} ({}) -> Reaction vote refused: {}", submitter.user_name, submitter.user_id, outcome);
+        return;
+    }
+    if data.democracy.cast_reaction(reaction.message_id, submitter.user_id, &reaction.emoji) {
+        dispatch::record_limits(data, submitter.user_id, submitter.guild_id, submitter.source);
+    }
+}
+
+fn tally_embed(title: &str, status: &str, election: &Election) -> CreateEmbed {
+    let tally = election.tally();
+    let votes = if tally.is_empty() {
+        "No votes yet, use a movement command or react below".to_owned()
+    } else {
+        tally.iter()
+            .map(|(choice, count)| format!("**{}** - {} vote(s)", choice, count))
+            .collect::<Vec<String>>()
+            .join("\n")
+    };
+
+    CreateEmbed::default()
+        .title(title.to_owned())
+        .description(status.to_owned())
+        .field("**Tally**", votes, false)
+        .color(Color::BLURPLE)
+        .timestamp(Timestamp::now())
+}
+
+/// Runs voting windows while in democracy mode and queues each window's winner
+pub async fn run(http: Arc<serenity::Http>, democracy: Arc<Democracy>, scheduler: Arc<Scheduler>, config: Arc<Mutex<Config>>, bot_id: UserId) {
+    loop {
+        let window_start = {
+            let state = democracy.state.lock().unwrap();
+            match (state.mode, state.channel) {
+                (Mode::Democracy, Some(channel)) => Some((channel, state.switches)),
+                _ => None,
+            }
+        };
+        let Some((channel, switches)) = window_start else {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            continue;
+        };
+
+        let (window, action_duration) = {
+            let config = config.lock().unwrap();
+            (config.democracy.window_secs.max(1), config.democracy.action_duration.min(config.queue.max_duration))
+        };
+        let ends = SystemTime::now() + Duration::from_secs(window);
+        let ends_unix = ends.duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs());
+        let status = format!("Voting ends <t:{}:R>", ends_unix);
+
+        // Votes cast between windows count towards this one
+        let embed = {
+            let state = democracy.state.lock().unwrap();
+            tally_embed("Vote for the next action", &status, &state.election)
+        };
+        let message = match channel.send_message(&http, CreateMessage::new().embed(embed)).await {
+            Ok(message) => message,
+            Err(e) => {
+                error!("Failed to post democracy tally: {}", e);
+                tokio::time::sleep(Duration::from_secs(window)).await;
+                continue;
+            }
+        };
+        democracy.state.lock().unwrap().message = Some(message.id);
+        for emoji in VOTE_EMOJIS {
+            if let Err(e) = message.react(&http, ReactionType::Unicode(emoji.to_owned())).await {
+                error!("Failed to add {} to democracy tally: {}", emoji, e);
+            }
+        }
+
+        // Keep the tally live until the window closes or the mode changes
+        let mut discarded = false;
+        while SystemTime::now() < ends {
+            tokio::time::sleep(REFRESH_INTERVAL.min(ends.duration_since(SystemTime::now()).unwrap_or_default())).await;
+            let embed = {
+                let mut state = democracy.state.lock().unwrap();
+                if state.switches != switches {
+                    discarded = true;
+                    break;
+                }
+                if !state.changed {
+                    continue;
+                }
+                state.changed = false;
+                tally_embed("Vote for the next action", &status, &state.election)
+            };
+            if let Err(e) = channel.edit_message(&http, message.id, EditMessage::new().embed(embed)).await {
+                error!("Failed to update democracy tally: {}", e);
+            }
+        }
+
+        let election = {
+            let mut state = democracy.state.lock().unwrap();
+            state.message = None;
+            discarded |= state.switches != switches;
+            if discarded {
+                Election::default()
+            } else {
+                state.changed = false;
+                std::mem::take(&mut state.election)
+            }
+        };
+
+        let status = match election.winner() {
+            _ if discarded => "Discarded, the mode was changed".to_owned(),
+            Some(choice) => {
+                let action = choice.to_action(action_duration);
+                match scheduler.push(bot_id, "Democracy".to_owned(), Lane::Priority, action, usize::MAX) {
+                    Ok(ticket) => format!("**{}** won with {} voter(s), queue position {}", choice, election.voters(), ticket.position),
+                    Err(e) => format!("**{}** won but couldn't be queued: {}", choice, e),
+                }
+            }
+            None => "Nobody voted".to_owned(),
+        };
+        let embed = tally_embed("Voting closed", &status, &election);
+        if let Err(e) = channel.edit_message(&http, message.id, EditMessage::new().embed(embed)).await {
+            error!("Failed to close democracy tally: {}", e);
+        }
+    }
+}
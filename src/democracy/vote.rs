@@ -0,0 +1,210 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::vrc_client::Action;
+
+/// Capitalizes the first letter of a direction and makes everything else lowercase
+fn normalize_direction(direction: &str) -> String {
+    let mut chars = direction.trim().chars();
+    match chars.next() {
+        None => String::new(),
+        Some(first) => first.to_uppercase().collect::<String>() + chars.as_str().to_lowercase().as_str(),
+    }
+}
+
+/// What a vote is cast for, an action without its duration
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Choice {
+    pub movement: Option<String>,
+    pub look: Option<String>,
+    pub run: bool,
+    pub jump: bool,
+}
+
+impl Choice {
+    /// None if the action doesn't do anything
+    pub fn from_action(action: &Action) -> Option<Self> {
+        let choice = Choice {
+            movement: action.movement.as_deref().map(normalize_direction),
+            look: action.look.as_deref().map(normalize_direction),
+            run: action.run.unwrap_or(false),
+            jump: action.jump.unwrap_or(false),
+        };
+        if choice.movement.is_none() && choice.look.is_none() && !choice.run && !choice.jump {
+            return None;
+        }
+        Some(choice)
+    }
+
+    pub fn to_action(&self, duration: u64) -> Action {
+        Action {
+            duration,
+            movement: self.movement.clone(),
+            look: self.look.clone(),
+            run: self.run.then_some(true),
+            jump: self.jump.then_some(true),
+        }
+    }
+
+    /// The choice a reaction on the voting embed stands for
+    pub fn from_emoji(emoji: &str) -> Option<Self> {
+        let (movement, look, run, jump) = match emoji {
+            "⬆️" => (Some("Forward"), None, false, false),
+            "⬇️" => (Some("Backward"), None, false, false),
+            "⬅️" => (Some("Left"), None, false, false),
+            "➡️" => (Some("Right"), None, false, false),
+            "↩️" => (None, Some("Left"), false, false),
+            "↪️" => (None, Some("Right"), false, false),
+            "🏃" => (None, None, true, false),
+            "🦘" => (None, None, false, true),
+            _ => return None,
+        };
+        Some(Choice {
+            movement: movement.map(str::to_owned),
+            look: look.map(str::to_owned),
+            run,
+            jump,
+        })
+    }
+}
+
+/// Reactions added to the voting embed, in display order
+pub const VOTE_EMOJIS: [&str; 8] = ["⬆️", "⬇️", "⬅️", "➡️", "↩️", "↪️", "🏃", "🦘"];
+
+impl fmt::Display for Choice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts: Vec<String> = Vec::new();
+        if let Some(movement) = &self.movement {
+            parts.push(format!("move {}", movement));
+        }
+        if let Some(look) = &self.look {
+            parts.push(format!("look {}", look));
+        }
+        if self.run {
+            parts.push("run".to_owned());
+        }
+        if self.jump {
+            parts.push("jump".to_owned());
+        }
+        write!(f, "{}", parts.join(" + "))
+    }
+}
+
+/// Votes collected during one window, one vote per user where the latest vote counts
+#[derive(Default)]
+pub struct Election {
+    votes: HashMap<u64, Choice>,
+    first_seen: Vec<Choice>, // Choices in the order they were first voted for, used to break ties
+}
+
+impl Election {
+    /// Returns true if the voter changed an earlier vote
+    pub fn cast(&mut self, voter: u64, choice: Choice) -> bool {
+        if !self.first_seen.contains(&choice) {
+            self.first_seen.push(choice.clone());
+        }
+        match self.votes.insert(voter, choice.clone()) {
+            Some(previous) => previous != choice,
+            None => false,
+        }
+    }
+
+    pub fn voters(&self) -> usize {
+        self.votes.len()
+    }
+
+    /// Choices with at least one vote, most votes first, ties broken by whichever was voted for first
+    pub fn tally(&self) -> Vec<(Choice, usize)> {
+        let mut counts: HashMap<&Choice, usize> = HashMap::new();
+        for choice in self.votes.values() {
+            *counts.entry(choice).or_default() += 1;
+        }
+
+        let mut tally: Vec<(Choice, usize)> = self.first_seen.iter()
+            .filter_map(|choice| counts.get(choice).map(|count| (choice.clone(), *count)))
+            .collect();
+        // Stable sort keeps first-seen order between equal counts
+        tally.sort_by_key(|(_, count)| Reverse(*count));
+        tally
+    }
+
+    pub fn winner(&self) -> Option<Choice> {
+        self.tally().into_iter().next().map(|(choice, _)| choice)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn movement(direction: &str) -> Choice {
+        Choice { movement: Some(direction.to_owned()), look: None, run: false, jump: false }
+    }
+
+    #[test]
+    fn empty_election_has_no_winner() {
+        assert_eq!(Election::default().winner(), None);
+    }
+
+    #[test]
+    fn most_votes_wins() {
+        let mut election = Election::default();
+        election.cast(1, movement("Left"));
+        election.cast(2, movement("Forward"));
+        election.cast(3, movement("Forward"));
+
+        assert_eq!(election.winner(), Some(movement("Forward")));
+        assert_eq!(election.tally(), vec![(movement("Forward"), 2), (movement("Left"), 1)]);
+    }
+
+    #[test]
+    fn latest_vote_replaces_earlier_vote() {
+        let mut election = Election::default();
+        assert!(!election.cast(1, movement("Left")));
+        assert!(election.cast(1, movement("Right")));
+        assert!(!election.cast(1, movement("Right")));
+
+        assert_eq!(election.voters(), 1);
+        assert_eq!(election.tally(), vec![(movement("Right"), 1)]);
+    }
+
+    #[test]
+    fn ties_go_to_first_choice_voted_for() {
+        let mut election = Election::default();
+        election.cast(1, movement("Backward"));
+        election.cast(2, movement("Forward"));
+
+        assert_eq!(election.winner(), Some(movement("Backward")));
+    }
+
+    #[test]
+    fn actions_normalize_to_the_same_choice() {
+        let lower = Action { duration: 1, movement: Some("forward".to_owned()), look: None, run: None, jump: None };
+        let upper = Action { duration: 9, movement: Some("FORWARD".to_owned()), look: None, run: Some(false), jump: None };
+
+        assert_eq!(Choice::from_action(&lower), Choice::from_action(&upper));
+        assert_eq!(Choice::from_action(&lower), Some(movement("Forward")));
+    }
+
+    #[test]
+    fn empty_action_is_not_a_choice() {
+        let action = Action { duration: 1, movement: None, look: None, run: Some(false), jump: None };
+        assert_eq!(Choice::from_action(&action), None);
+    }
+
+    #[test]
+    fn every_vote_emoji_maps_to_a_choice() {
+        for emoji in VOTE_EMOJIS {
+            assert!(Choice::from_emoji(emoji).is_some(), "{} has no choice", emoji);
+        }
+        assert_eq!(Choice::from_emoji("🦘").map(|choice| choice.jump), Some(true));
+        assert_eq!(Choice::from_emoji("🍕"), None);
+    }
+
+    #[test]
+    fn choice_round_trips_through_action() {
+        let choice = Choice { movement: Some("Left".to_owned()), look: Some("Right".to_owned()), run: true, jump: false };
+        assert_eq!(Choice::from_action(&choice.to_action(3)), Some(choice));
+    }
+}
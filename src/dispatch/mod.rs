@@ -0,0 +1,167 @@
+use std::fmt;
+
+use poise::serenity_prelude::{ChannelId, GuildId, Reaction, UserId};
+
+use crate::democracy::Mode;
+use crate::democracy::vote::Choice;
+use crate::limits::LimitHit;
+use crate::permissions::{self, Capability};
+use crate::scheduler::Lane;
+use crate::utils;
+use crate::vrc_client::Action;
+use crate::Data;
+
+/// Who an action sent outside of a command comes from
+pub struct Submitter {
+    pub user_id: UserId,
+    pub user_name: String,
+    pub role_ids: Vec<String>,
+    pub channel_id: ChannelId,
+    pub guild_id: Option<GuildId>,
+    pub source: &'static str, // Name [limits.commands] rules use for this input, like a command name
+}
+
+impl Submitter {
+    /// The user behind a reaction, None for reactions without a user
+    pub fn from_reaction(reaction: &Reaction, source: &'static str) -> Option<Self> {
+        let user_id = reaction.user_id?;
+        let member = reaction.member.as_ref();
+        Some(Submitter {
+            user_id,
+            user_name: member.map_or_else(|| user_id.to_string(), |member| member.user.name.clone()),
+            role_ids: member
+                .map(|member| member.roles.iter().map(|role_id| role_id.to_string()).collect())
+                .unwrap_or_default(),
+            channel_id: reaction.channel_id,
+            guild_id: reaction.guild_id,
+            source,
+        })
+    }
+}
+
+/// What happened to a submitted action
+pub enum Outcome {
+    Queued(usize), // 1-based queue position
+    Voted(Choice),
+    Denied,  // Missing the movement capability
+    Locked,  // Someone else has control
+    Full,    // The user's pending limit is reached
+    Limited(LimitHit),
+    WrongChannel, // The channel isn't on the allowlist
+    Ignored, // Blocked user, or an action that doesn't do anything
+}
+
+impl Outcome {
+    /// Reaction used to acknowledge the outcome on a message
+    pub fn emoji(&self) -> Option<&'static str> {
+        match self {
+            Outcome::Queued(_) => Some("✅"),
+            Outcome::Voted(_) => Some("🗳️"),
+            Outcome::Denied => Some("🚫"),
+            Outcome::Locked => Some("🔒"),
+            Outcome::Full => Some("⏳"),
+            Outcome::Limited(_) => Some("🐢"),
+            Outcome::WrongChannel => None, // Not acknowledged so the channel isn't spammed with reactions
+            Outcome::Ignored => None,
+        }
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Outcome::Queued(position) => write!(f, "Queued at position {}", position),
+            Outcome::Voted(choice) => write!(f, "Voted for {}", choice),
+            Outcome::Denied => write!(f, "Missing the movement capability"),
+            Outcome::Locked => write!(f, "Someone else has control"),
+            Outcome::Full => write!(f, "Too many actions waiting"),
+            Outcome::Limited(hit) => write!(f, "Slow down, {} (wait {})", hit, utils::format_duration(hit.wait)),
+            Outcome::WrongChannel => write!(f, "Control isn't accepted in this channel"),
+            Outcome::Ignored => write!(f, "Ignored"),
+        }
+    }
+}
+
+/// Checks the configured limits for a user, owners aren't subject to them
+pub fn check_limits(data: &Data, user_id: UserId, guild_id: Option<GuildId>, command: &str) -> Result<(), LimitHit> {
+    let limits = {
+        let config = data.config.lock().unwrap();
+        if config.is_owner(user_id.get()) {
+            return Ok(());
+        }
+        config.limits.clone()
+    };
+    data.limiter.check(&limits, user_id.get(), guild_id.map(|guild_id| guild_id.get()), command)
+}
+
+/// Uses up the limits once an action or vote was accepted
+pub fn record_limits(data: &Data, user_id: UserId, guild_id: Option<GuildId>, command: &str) {
+    let limits = {
+        let config = data.config.lock().unwrap();
+        if config.is_owner(user_id.get()) {
+            return;
+        }
+        config.limits.clone()
+    };
+    data.limiter.record(&limits, user_id.get(), guild_id.map(|guild_id| guild_id.get()), command);
+}
+
+/// Runs the checks every action and vote goes through, returning the outcome to report if one fails
+pub fn admit(data: &Data, submitter: &Submitter) -> Result<(), Outcome> {
+    if data.access.is_blocked(submitter.user_id.get()) {
+        return Err(Outcome::Ignored);
+    }
+    if !data.access.is_channel_allowed(submitter.channel_id.get()) {
+        return Err(Outcome::WrongChannel);
+    }
+
+    let may_move = {
+        let config = data.config.lock().unwrap();
+        let capabilities = permissions::capabilities_for(&config, submitter.user_id.get(), &submitter.role_ids, submitter.channel_id.get());
+        capabilities.contains(&Capability::Admin) || capabilities.contains(&Capability::Movement)
+    };
+    if !may_move {
+        return Err(Outcome::Denied);
+    }
+
+    check_limits(data, submitter.user_id, submitter.guild_id, submitter.source).map_err(Outcome::Limited)
+}
+
+/// Sends an action from text, reactions or components through the same checks as movement commands
+pub fn submit(data: &Data, submitter: &Submitter, mut action: Action) -> Outcome {
+    if let Err(outcome) = admit(data, submitter) {
+        return outcome;
+    }
+
+    let (is_owner, lane, max_pending_per_user) = {
+        let config = data.config.lock().unwrap();
+        let is_owner = config.is_owner(submitter.user_id.get());
+        let lane = if is_owner || submitter.role_ids.contains(&config.queue.priority_role) { Lane::Priority } else { Lane::Normal };
+        // Panel, controller and text durations come from the config, which may allow longer than the queue does
+        action.duration = action.duration.min(config.queue.max_duration);
+        (is_owner, lane, config.queue.max_pending_per_user)
+    };
+
+    if data.democracy.mode() == Mode::Democracy {
+        return match Choice::from_action(&action) {
+            Some(choice) => {
+                data.democracy.cast(submitter.user_id, choice.clone());
+                record_limits(data, submitter.user_id, submitter.guild_id, submitter.source);
+                Outcome::Voted(choice)
+            }
+            None => Outcome::Ignored,
+        };
+    }
+
+    if !is_owner && data.control.may_drive(submitter.user_id).is_err() {
+        return Outcome::Locked;
+    }
+
+    match data.scheduler.push(submitter.user_id, submitter.user_name.clone(), lane, action, max_pending_per_user) {
+        Ok(ticket) => {
+            record_limits(data, submitter.user_id, submitter.guild_id, submitter.source);
+            Outcome::Queued(ticket.position)
+        }
+        Err(_) => Outcome::Full,
+    }
+}
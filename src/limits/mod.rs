@@ -0,0 +1,244 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::config::{LimitRule, Limits};
+use crate::utils;
+
+const SECONDS_PER_DAY: u64 = 86_400;
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs())
+}
+
+/// Usage of a single limited key (a user, a guild or a user's command)
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct Usage {
+    pub last: u64,        // Unix time of the last use
+    pub recent: Vec<u64>, // Unix times of uses inside the longest rate window
+    pub day: u64,         // Day number (unix time / 86400) the daily count belongs to
+    pub today: u32,       // Uses during `day`
+}
+
+impl Usage {
+    /// Seconds until this key may be used again under `rule`, None if it may be used now
+    fn wait(&self, rule: &LimitRule, now: u64) -> Option<(LimitKind, u64)> {
+        // Saturating so huge configured durations block for good instead of overflowing
+        let cooled = self.last.saturating_add(rule.cooldown);
+        if rule.cooldown > 0 && cooled > now {
+            return Some((LimitKind::Cooldown, cooled - now));
+        }
+
+        if rule.rate_limit > 0 && rule.rate_window > 0 {
+            let in_window: Vec<u64> = self.recent.iter()
+                .copied()
+                .filter(|used| used.saturating_add(rule.rate_window) > now)
+                .collect();
+            if in_window.len() >= rule.rate_limit as usize {
+                // Wait for the oldest use to leave the window
+                let oldest = in_window.iter().min().copied().unwrap_or(now);
+                return Some((LimitKind::RateLimit, oldest.saturating_add(rule.rate_window) - now));
+            }
+        }
+
+        let day = now / SECONDS_PER_DAY;
+        if rule.daily_quota > 0 && self.day == day && self.today >= rule.daily_quota {
+            return Some((LimitKind::DailyQuota, (day + 1) * SECONDS_PER_DAY - now));
+        }
+
+        None
+    }
+
+    fn record(&mut self, rule: &LimitRule, now: u64) {
+        self.last = now;
+
+        self.recent.retain(|used| used.saturating_add(rule.rate_window) > now);
+        if rule.rate_limit > 0 {
+            self.recent.push(now);
+        }
+
+        let day = now / SECONDS_PER_DAY;
+        if self.day != day {
+            self.day = day;
+            self.today = 0;
+        }
+        self.today = self.today.saturating_add(1);
+    }
+}
+
+/// Everything that is persisted between restarts
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct LimitState {
+    pub users: HashMap<String, Usage>,
+    pub guilds: HashMap<String, Usage>,
+    pub commands: HashMap<String, Usage>, // Keyed by "<user id>:<command name>"
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LimitKind {
+    Cooldown,
+    RateLimit,
+    DailyQuota,
+}
+
+#[derive(Debug)]
+pub struct LimitHit {
+    pub scope: String, // "user", "guild" or the command name
+    pub kind: LimitKind,
+    pub wait: u64, // Seconds until the limit clears
+}
+
+impl fmt::Display for LimitHit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            LimitKind::Cooldown => "cooldown",
+            LimitKind::RateLimit => "rate limit",
+            LimitKind::DailyQuota => "daily quota",
+        };
+        write!(f, "{} {} reached", self.scope, kind)
+    }
+}
+
+/// Tracks cooldowns, rate limits and daily quotas, saving usage to disk after every change
+pub struct Limiter {
+    path: PathBuf,
+    state: Mutex<(LimitState, u64)>, // Along with how many times it has changed
+    saved: Mutex<u64>,               // Change last written to disk, so an older save never overwrites a newer one
+}
+
+impl Limiter {
+    pub fn load(path: &str) -> Self {
+        let path = PathBuf::from(path);
+        Limiter {
+            state: Mutex::new((utils::load_state(&path, "limits"), 0)),
+            saved: Mutex::new(0),
+            path,
+        }
+    }
+
+    /// Checks every applicable limit without using any of them up
+    pub fn check(&self, limits: &Limits, user_id: u64, guild_id: Option<u64>, command: &str) -> Result<(), LimitHit> {
+        let now = unix_now();
+        let hit = |scope: &str, (kind, wait): (LimitKind, u64)| LimitHit { scope: scope.to_owned(), kind, wait };
+        let state = &self.state.lock().unwrap().0;
+
+        if let Some(wait) = state.users.get(&user_id.to_string()).and_then(|usage| usage.wait(&limits.user, now)) {
+            return Err(hit("user", wait));
+        }
+        if let Some(guild_id) = guild_id {
+            if let Some(wait) = state.guilds.get(&guild_id.to_string()).and_then(|usage| usage.wait(&limits.guild, now)) {
+                return Err(hit("guild", wait));
+            }
+        }
+        if let Some(rule) = limits.commands.get(command) {
+            if let Some(wait) = state.commands.get(&format!("{}:{}", user_id, command)).and_then(|usage| usage.wait(rule, now)) {
+                return Err(hit(command, wait));
+            }
+        }
+        Ok(())
+    }
+
+    /// Records a use against every enabled limit, call once the action was accepted
+    pub fn record(&self, limits: &Limits, user_id: u64, guild_id: Option<u64>, command: &str) {
+        let now = unix_now();
+        let user_enabled = limits.user.is_enabled();
+        let guild_id = guild_id.filter(|_| limits.guild.is_enabled());
+        let command_rule = limits.commands.get(command).filter(|rule| rule.is_enabled());
+        if !user_enabled && guild_id.is_none() && command_rule.is_none() {
+            return;
+        }
+
+        let (state, change) = {
+            let mut guard = self.state.lock().unwrap();
+            let (state, changes) = &mut *guard;
+            if user_enabled {
+                state.users.entry(user_id.to_string()).or_default().record(&limits.user, now);
+            }
+            if let Some(guild_id) = guild_id {
+                state.guilds.entry(guild_id.to_string()).or_default().record(&limits.guild, now);
+            }
+            if let Some(rule) = command_rule {
+                state.commands.entry(format!("{}:{}", user_id, command)).or_default().record(rule, now);
+            }
+            *changes += 1;
+            (state.clone(), *changes)
+        };
+        self.save(&state, change);
+    }
+
+    fn save(&self, state: &LimitState, change: u64) {
+        let mut saved = self.saved.lock().unwrap();
+        if change > *saved && utils::save_state(&self.path, "limits", state) {
+            *saved = change;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Noon on some UTC day, so a few hours either way stay on the same day
+    const NOON: u64 = 20_000 * SECONDS_PER_DAY + SECONDS_PER_DAY / 2;
+
+    fn rule(cooldown: u64, rate_limit: u32, rate_window: u64, daily_quota: u32) -> LimitRule {
+        LimitRule { cooldown, rate_limit, rate_window, daily_quota }
+    }
+
+    #[test]
+    fn cooldown_waits_from_the_last_use() {
+        let rule = rule(10, 0, 0, 0);
+        let mut usage = Usage::default();
+        assert_eq!(usage.wait(&rule, NOON), None);
+
+        usage.record(&rule, NOON);
+        assert_eq!(usage.wait(&rule, NOON), Some((LimitKind::Cooldown, 10)));
+        assert_eq!(usage.wait(&rule, NOON + 7), Some((LimitKind::Cooldown, 3)));
+        assert_eq!(usage.wait(&rule, NOON + 10), None);
+    }
+
+    #[test]
+    fn rate_limit_waits_for_the_oldest_use_to_leave_the_window() {
+        let rule = rule(0, 2, 60, 0);
+        let mut usage = Usage::default();
+        usage.record(&rule, NOON);
+        assert_eq!(usage.wait(&rule, NOON + 20), None);
+
+        usage.record(&rule, NOON + 20);
+        assert_eq!(usage.wait(&rule, NOON + 30), Some((LimitKind::RateLimit, 30)));
+        assert_eq!(usage.wait(&rule, NOON + 60), None);
+
+        // Uses which left the window are forgotten
+        usage.record(&rule, NOON + 100);
+        assert_eq!(usage.recent, vec![NOON + 100]);
+    }
+
+    #[test]
+    fn daily_quota_resets_at_midnight_utc() {
+        let rule = rule(0, 0, 0, 2);
+        let mut usage = Usage::default();
+        usage.record(&rule, NOON);
+        usage.record(&rule, NOON + 1);
+        assert_eq!(usage.wait(&rule, NOON + 2), Some((LimitKind::DailyQuota, SECONDS_PER_DAY / 2 - 2)));
+
+        let tomorrow = NOON + SECONDS_PER_DAY;
+        assert_eq!(usage.wait(&rule, tomorrow), None);
+        usage.record(&rule, tomorrow);
+        assert_eq!(usage.today, 1);
+    }
+
+    #[test]
+    fn huge_durations_block_without_overflowing() {
+        let rule = rule(u64::MAX, 1, u64::MAX, 0);
+        let mut usage = Usage::default();
+        usage.record(&rule, NOON);
+        assert_eq!(usage.wait(&rule, NOON + 1), Some((LimitKind::Cooldown, u64::MAX - NOON - 1)));
+
+        let rule = LimitRule { cooldown: 0, ..rule };
+        usage.record(&rule, NOON + 2);
+        assert_eq!(usage.wait(&rule, NOON + 3).map(|(kind, _)| kind), Some(LimitKind::RateLimit));
+    }
+}
@@ -1,18 +1,28 @@
 #![warn(clippy::str_to_string)]
 
 // Functionality imports
+mod access;
+mod bridge;
+mod chatbox;
 mod commands;
 mod config;
+mod control;
+mod democracy;
+mod dispatch;
+mod limits;
+mod panel;
+mod permissions;
+mod scheduler;
+mod text_control;
+mod timers;
 mod utils;
 mod vrc_client;
 
-use tokio;
-use tokio::sync::mpsc;
 
 // Poise/Serenity imports
 use poise::serenity_prelude as serenity;
 use std::sync::{Arc, Mutex};
-use crate::vrc_client::Action;
+use crate::scheduler::Scheduler;
 use crate::vrc_client::traits::Input;
 
 // Types used by all command functions
@@ -22,7 +32,18 @@ type Context<'a> = poise::Context<'a, Data, Error>;
 // Custom user data passed to all command functions
 pub struct Data {
     config: Arc<Mutex<config::Config>>,
-    vrc_transmitter: tokio::sync::mpsc::Sender<Action>,
+    scheduler: Arc<Scheduler>,
+    limiter: limits::Limiter,
+    access: access::Access,
+    control: Arc<control::ControlLock>,
+    democracy: Arc<democracy::Democracy>,
+    text_throttle: text_control::Throttle,
+    panel_throttle: text_control::Throttle,
+    panels: panel::Panels,
+    chatbox: Arc<chatbox::manager::ChatboxManager>,
+    status: Arc<chatbox::status::Sources>,
+    bridge: bridge::Bridge,
+    timers: Arc<timers::Timers>,
 }
 
 async fn on_error(error: poise::FrameworkError<'_, Data, Error>) {
@@ -45,13 +66,28 @@ async fn on_error(error: poise::FrameworkError<'_, Data, Error>) {
 #[tokio::main]
 async fn main() {
     env_logger::init();
+    let started = std::time::Instant::now();
 
-    // Parse the config
-    let config = config::Config::new();
+    // Parse the config, --check-config stops here with 0 for a valid config and 1 otherwise
+    let check_only = std::env::args().any(|arg| arg == "--check-config");
+    let config = match config::Config::new() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    if check_only {
+        println!("Config is valid");
+        return;
+    }
     config.print();
 
-    let mention_as_prefix = config.options.mention_as_prefix.clone();
-    let token = config.auth.token.clone();
+    let mention_as_prefix = config.options.mention_as_prefix;
+    let limiter = limits::Limiter::load(&config.limits.state_file);
+    let access = access::Access::load(&config.access.state_file);
+    let panels = panel::Panels::load(&config.panel.state_file);
+    let token = config.auth.token.expose().to_owned();
 
     // Wrap the config in an Arc<Mutex<>>
     let config = Arc::new(Mutex::new(config));
@@ -63,7 +99,41 @@ async fn main() {
 
     let vrc_client = Arc::new(vrc_client::client::Client::new(receiver_port, transmitter_port));
 
-    let (vrc_transmitter, mut vrc_receiver) = mpsc::channel::<Action>(64);
+    // Every chatbox write goes through the manager, which paces them for VRChat
+    let chatbox = Arc::new(chatbox::manager::ChatboxManager::new());
+    let chatbox_status = Arc::clone(&chatbox); // For the chatbox status
+    tokio::spawn(chatbox::manager::run(Arc::clone(&chatbox), Arc::clone(&vrc_client), Arc::clone(&config)));
+
+    // Actions from every user are queued here and served fairly by the movement handler
+    let scheduler = Arc::new(Scheduler::new());
+    let scheduler_clone = Arc::clone(&scheduler); // For the movement handler
+
+    // Exclusive controller lock, expired by the control timer and shown by the message spammer
+    let control = Arc::new(control::ControlLock::new());
+    let control_clone = Arc::clone(&control); // For the control timer
+
+    // Live data shown in the chatbox status rotation
+    let status_sources = Arc::new(chatbox::status::Sources {
+        control: Arc::clone(&control),
+        scheduler: Arc::clone(&scheduler),
+        started,
+        system: chatbox::widgets::SystemStats::new(),
+        now_playing: Mutex::new(None),
+    });
+    let status_sources_clone = Arc::clone(&status_sources); // For the chatbox status
+
+    // Vote collection for democracy mode, windows are run once the bot is logged in
+    let democracy = Arc::new(democracy::Democracy::new());
+    let democracy_scheduler = Arc::clone(&scheduler);
+    let democracy_config = Arc::clone(&config);
+
+    // Timers and countdowns, shown in the chatbox and announced once the bot is logged in
+    let timers = Arc::new(timers::Timers::new());
+    let timers_chatbox = Arc::clone(&chatbox);
+    let timers_config = Arc::clone(&config);
+
+    // The config file is watched and reloaded on change, results are posted once the bot is logged in
+    let reload_config = Arc::clone(&config);
 
     // FrameworkOptions contains all of poise's configuration option in one struct
     // Every option can be omitted to use its default value
@@ -76,6 +146,28 @@ async fn main() {
             commands::vrc::run(),
             commands::vrc::jump(),
             commands::vrc::action_combined(),
+            commands::queue::queue(),
+            commands::access::block(),
+            commands::access::unblock(),
+            commands::access::allow_channel(),
+            commands::access::disallow_channel(),
+            commands::access::access(),
+            commands::control::takecontrol(),
+            commands::control::releasecontrol(),
+            commands::control::forcecontrol(),
+            commands::control::rotation(),
+            commands::control::driver(),
+            commands::democracy::mode(),
+            commands::panel::panel(),
+            commands::controller::controller(),
+            commands::chatbox::say(),
+            commands::chatbox::send_to_chatbox(),
+            commands::chatbox::chatbox(),
+            commands::bridge::bridge(),
+            commands::config::config(),
+            commands::timer::timer(),
+            commands::timer::countdown(),
+            commands::timer::canceltimer(),
         ],
         prefix_options: poise::PrefixFrameworkOptions {
             //edit_tracker: Some(Arc::new(poise::EditTracker::for_timespan(
@@ -102,23 +194,37 @@ async fn main() {
             })
         },
         // Every command invocation must pass this check to continue execution
-        //command_check: Some(|ctx| {
-        //    Box::pin(async move {
-        //        if ctx.author().id == 123456789 {
-        //            return Ok(false);
-        //        }
-        //        Ok(true)
-        //    })
-        //}),
+        // Blocked users and disallowed channels are rejected before permissions are checked
+        // Owners from the config skip them too, they are looked up live so reloads and /config set apply right away
+        command_check: Some(|ctx| {
+            Box::pin(async move {
+                Ok(permissions::is_owner(ctx) || (access::check(ctx).await? && permissions::check(ctx).await?))
+            })
+        }),
+        // Only the application owner is known to poise, owner commands check auth.owners through permissions::owners_only
         // Enforce command checks even for owners (enforced by default)
         // Set to true to bypass checks, which is useful for testing
         skip_checks_for_owners: true,
-        event_handler: |_ctx, event, _framework, _data| {
+        event_handler: |ctx, event, _framework, data| {
             Box::pin(async move {
                 println!(
                     "Got an event in event handler: {:?}",
                     event.snake_case_name()
                 );
+
+                match event {
+                    // Reactions on the democracy tally are votes, reactions on control panels are actions
+                    serenity::FullEvent::ReactionAdd { add_reaction } => {
+                        democracy::handle_reaction(ctx, data, add_reaction).await;
+                        panel::handle_reaction(ctx, data, add_reaction).await;
+                    }
+                    // Bare messages in the text control channel are actions, messages in the bridged channel go to the chatbox
+                    serenity::FullEvent::Message { new_message } => {
+                        text_control::handle_message(ctx, data, new_message).await;
+                        bridge::handle_message(ctx, data, new_message).await;
+                    }
+                    _ => {}
+                }
                 Ok(())
             })
         },
@@ -130,9 +236,31 @@ async fn main() {
             Box::pin(async move {
                 println!("Logged in as {}", _ready.user.name);
                 poise::builtins::register_globally(ctx, &framework.options().commands).await?;
+
+                tokio::spawn(democracy::run(
+                    ctx.http.clone(),
+                    Arc::clone(&democracy),
+                    democracy_scheduler,
+                    democracy_config,
+                    _ready.user.id,
+                ));
+                tokio::spawn(config::reload::run(ctx.http.clone(), reload_config));
+                tokio::spawn(timers::run(ctx.http.clone(), Arc::clone(&timers), timers_chatbox, timers_config));
+
                 Ok(Data {
                     config,
-                    vrc_transmitter,
+                    scheduler,
+                    limiter,
+                    access,
+                    control,
+                    democracy,
+                    text_throttle: text_control::Throttle::new(),
+                    panel_throttle: text_control::Throttle::new(),
+                    panels,
+                    chatbox,
+                    status: status_sources,
+                    bridge: bridge::Bridge::new(),
+                    timers,
                 })
             })
         })
@@ -147,43 +275,28 @@ async fn main() {
         .await;
 
     // First tokio::spawn (movement handler)
-    // All spawned tasks allow for asynchronous movement queuing
+    // Actions are performed one at a time in the order the scheduler hands them out
     let vrc_client_clone = Arc::clone(&vrc_client);
     tokio::spawn(async move {
         let vrc_client = Arc::clone(&vrc_client_clone);
-        while let Some(action) = vrc_receiver.recv().await {
+        loop {
+            let (queued, stop) = scheduler_clone.next().await;
+            let action = queued.action;
 
             // Horizontal character movement
-            if let Some(movement) = action.movement {
-                vrc_client.input_move(&movement, true);
-                let vrc_client_clone = Arc::clone(&vrc_client);
-                // Timer for asynchronous actions
-                tokio::spawn(async move {
-                    tokio::time::sleep(std::time::Duration::from_secs(action.duration)).await;
-                    vrc_client_clone.input_move(&movement, false);
-                });
+            if let Some(movement) = &action.movement {
+                vrc_client.input_move(movement, true);
             }
 
             // Horizontal view angle movement
-            if let Some(look) = action.look {
-                vrc_client.input_look(&look, true);
-                let vrc_client_clone = Arc::clone(&vrc_client);
-                // Timer for asynchronous actions
-                tokio::spawn(async move {
-                    tokio::time::sleep(std::time::Duration::from_secs(action.duration)).await;
-                    vrc_client_clone.input_look(&look, false);
-                });
+            if let Some(look) = &action.look {
+                vrc_client.input_look(look, true);
             }
 
             // Duration to run
-            if let Some(_) = action.run {
+            let run = action.run.unwrap_or(false);
+            if run {
                 vrc_client.input_run(1);
-                let vrc_client_clone = Arc::clone(&vrc_client);
-                // Timer for asynchronous actions
-                tokio::spawn(async move {
-                    tokio::time::sleep(std::time::Duration::from_secs(action.duration)).await;
-                    vrc_client_clone.input_run(0);
-                });
             }
 
             // Makes character jump
@@ -193,19 +306,47 @@ async fn main() {
                     vrc_client_clone.input_jump();
                 });
             }
+
+            // Hold the inputs for the action's duration before releasing them, unless stopped early
+            tokio::select! {
+                _ = tokio::time::sleep(std::time::Duration::from_secs(action.duration)) => {}
+                _ = stop.notified() => {}
+            }
+
+            if let Some(movement) = &action.movement {
+                vrc_client.input_move(movement, false);
+            }
+            if let Some(look) = &action.look {
+                vrc_client.input_look(look, false);
+            }
+            if run {
+                vrc_client.input_run(0);
+            }
+
+            scheduler_clone.finish();
         }
     });
 
-    // Second tokio::spawn (message spammer)
-    let vrc_client_clone = Arc::clone(&vrc_client);
+    // Second tokio::spawn (chatbox status)
+    // Rotates through the status templates, names the current driver while someone has taken control
+    // The now playing widget reads MPRIS players over D-Bus, which only exists on Linux
+    #[cfg(target_os = "linux")]
+    tokio::spawn(chatbox::now_playing::run(Arc::clone(&status_sources_clone), Arc::clone(&config_clone)));
+    #[cfg(not(target_os = "linux"))]
+    if config_clone.lock().unwrap().widgets.now_playing.enabled {
+        println!("The now playing widget is only supported on Linux and stays empty");
+    }
+    tokio::spawn(chatbox::file_source::run(Arc::clone(&chatbox_status), Arc::clone(&config_clone)));
+    tokio::spawn(chatbox::status::run(chatbox_status, config_clone, status_sources_clone));
+
+    // Third tokio::spawn (control timer)
+    // Ends expired turns and hands control to the next waiting user
     tokio::spawn(async move {
-        let vrc_client = Arc::clone(&vrc_client_clone);
-        let config = Arc::new(&config_clone);
         loop {
-            let message = config.lock().unwrap().options.message.clone();
-            vrc_client.chatbox_message(&message);
-            tokio::time::sleep(std::time::Duration::from_secs(3)).await;
-            config.lock().unwrap().update();
+            tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+            if let Some(driver) = control_clone.tick() {
+                println!("{} ({}) -> Took control through rotation", driver.user_name, driver.user_id);
+            }
         }
     });
 
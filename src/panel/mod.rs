@@ -0,0 +1,105 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use log::error;
+use poise::serenity_prelude::{self as serenity, MessageId, ReactionType};
+
+use crate::dispatch::{self, Submitter};
+use crate::utils;
+use crate::vrc_client::Action;
+use crate::Data;
+
+// Only the newest panels are remembered, older ones stop responding
+const MAX_PANELS: usize = 100;
+
+/// What is saved between restarts
+#[derive(Default, Deserialize, Serialize)]
+#[serde(default)]
+struct PanelList {
+    messages: Vec<String>, // Message IDs, oldest first
+}
+
+/// Control panel messages posted by /panel, saved to disk so they survive restarts
+pub struct Panels {
+    path: PathBuf,
+    messages: Mutex<Vec<MessageId>>,
+}
+
+impl Panels {
+    pub fn load(path: &str) -> Self {
+        let path = PathBuf::from(path);
+        let list: PanelList = utils::load_state(&path, "panels");
+        Panels {
+            messages: Mutex::new(list.messages.iter().filter_map(|id| id.parse().ok()).map(MessageId::new).collect()),
+            path,
+        }
+    }
+
+    pub fn add(&self, message_id: MessageId) {
+        let mut messages = self.messages.lock().unwrap();
+        messages.push(message_id);
+        if messages.len() > MAX_PANELS {
+            let excess = messages.len() - MAX_PANELS;
+            messages.drain(..excess);
+        }
+
+        let list = PanelList { messages: messages.iter().map(MessageId::to_string).collect() };
+        utils::save_state(&self.path, "panels", &list);
+    }
+
+    pub fn contains(&self, message_id: MessageId) -> bool {
+        self.messages.lock().unwrap().contains(&message_id)
+    }
+}
+
+/// Performs the action mapped to a reaction on a control panel and removes the reaction again
+pub async fn handle_reaction(ctx: &serenity::Context, data: &Data, reaction: &serenity::Reaction) {
+    let Some(user_id) = reaction.user_id else {
+        return;
+    };
+    if user_id == ctx.cache.current_user().id || !data.panels.contains(reaction.message_id) {
+        return;
+    }
+    let ReactionType::Unicode(emoji) = &reaction.emoji else {
+        return;
+    };
+
+    let (action, throttle, is_owner) = {
+        let config = data.config.lock().unwrap();
+        let action = config.panel.buttons.iter()
+            .find(|button| button.emoji == *emoji)
+            .map(|button| {
+                let mut action = Action {
+                    duration: config.panel.duration,
+                    movement: None,
+                    look: None,
+                    run: None,
+                    jump: None,
+                };
+                button.input.apply(&mut action);
+                action
+            });
+        (action, Duration::from_secs(config.panel.throttle_secs), config.is_owner(user_id.get()))
+    };
+
+    // Remove the reaction so the same button can be pressed again
+    if let Err(e) = reaction.delete(ctx).await {
+        error!("Failed to remove panel reaction: {}", e);
+    }
+
+    let Some(action) = action else {
+        return;
+    };
+
+    let Some(submitter) = Submitter::from_reaction(reaction, "panel") else {
+        return;
+    };
+    if !is_owner && !data.panel_throttle.allow(user_id, throttle) {
+        println!("{} ({}) -> Pressed {} on a control panel too soon, ignored", submitter.user_name, user_id, emoji);
+        return;
+    }
+    let outcome = dispatch::submit(data, &submitter, action);
+    println!("{} ({}) -> Pressed {} on a control panel: {}", submitter.user_name, user_id, emoji, outcome);
+}
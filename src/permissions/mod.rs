@@ -0,0 +1,131 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+
+use poise::serenity_prelude::{CreateEmbed, Color, Timestamp};
+use poise::CreateReply;
+
+use crate::config::Config;
+use crate::{Context, Error};
+
+/// Things a user can be allowed to do with the bot
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Capability {
+    Movement, // Movement, look, run and jump actions
+    Chatbox,  // Writing to the VRChat chatbox
+    Admin,    // Bot administration, implies every other capability
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Capability::Movement => "movement",
+            Capability::Chatbox => "chatbox",
+            Capability::Admin => "admin",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// The capability a command needs by qualified name, None if anyone may use it
+/// Commands missing from this list need admin, so a new command is never open by accident
+pub fn required_capability(command: &str) -> Option<Capability> {
+    match command {
+        "queue" | "driver" => None,
+        // Parents only group their subcommands, which are checked on their own
+        "chatbox" | "bridge" | "config" => None,
+        "move_horizontal" | "look" | "run" | "jump" | "action_combined" => Some(Capability::Movement),
+        "takecontrol" | "releasecontrol" | "panel" | "controller" => Some(Capability::Movement),
+        "say" | "send_to_chatbox" | "chatbox list" | "timer" | "countdown" | "canceltimer" => Some(Capability::Chatbox),
+        "bridge start" | "bridge stop" | "bridge pause" => Some(Capability::Admin),
+        "chatbox add" | "chatbox remove" | "chatbox interval" | "chatbox enable" | "chatbox disable" => Some(Capability::Admin),
+        "register" | "shutdown" | "config get" | "config set" | "config reload" => Some(Capability::Admin),
+        "block" | "unblock" | "allow_channel" | "disallow_channel" | "access" => Some(Capability::Admin),
+        "forcecontrol" | "rotation" | "mode" => Some(Capability::Admin),
+        _ => Some(Capability::Admin),
+    }
+}
+
+/// Collects every capability granted by default, by user, by role and by channel
+pub fn capabilities_for(config: &Config, user_id: u64, role_ids: &[String], channel_id: u64) -> HashSet<Capability> {
+    let permissions = &config.permissions;
+
+    if config.is_owner(user_id) {
+        return HashSet::from([Capability::Admin]);
+    }
+
+    let mut granted: HashSet<Capability> = permissions.default.iter().copied().collect();
+    if let Some(capabilities) = permissions.users.get(&user_id.to_string()) {
+        granted.extend(capabilities);
+    }
+    for role_id in role_ids {
+        if let Some(capabilities) = permissions.roles.get(role_id) {
+            granted.extend(capabilities);
+        }
+    }
+    if let Some(capabilities) = permissions.channels.get(&channel_id.to_string()) {
+        granted.extend(capabilities);
+    }
+    granted
+}
+
+async fn granted_capabilities(ctx: Context<'_>) -> HashSet<Capability> {
+    let role_ids: Vec<String> = match ctx.author_member().await {
+        Some(member) => member.roles.iter().map(|role_id| role_id.to_string()).collect(),
+        None => Vec::new(),
+    };
+
+    let config = ctx.data().config.lock().unwrap();
+    capabilities_for(&config, ctx.author().id.get(), &role_ids, ctx.channel_id().get())
+}
+
+/// Whether the caller has `capability`
+pub async fn has_capability(ctx: Context<'_>, capability: Capability) -> bool {
+    let granted = granted_capabilities(ctx).await;
+    granted.contains(&Capability::Admin) || granted.contains(&capability)
+}
+
+/// Whether the caller is in `auth.owners` or owns the application
+/// The config is read every time, so owners added or removed by a reload or /config set count right away
+pub fn is_owner(ctx: Context<'_>) -> bool {
+    ctx.data().config.lock().unwrap().is_owner(ctx.author().id.get()) || ctx.framework().options().owners.contains(&ctx.author().id)
+}
+
+/// Check for owner commands, used instead of poise's owners_only whose owner list is fixed at startup
+pub async fn owners_only(ctx: Context<'_>) -> Result<bool, Error> {
+    if is_owner(ctx) {
+        return Ok(true);
+    }
+
+    let reply_embed = CreateEmbed::default()
+        .title("Permission denied")
+        .field("**Caller**", format!("{} ({})\nOnly bot owners can use this command.", ctx.author().name, ctx.author().id), false)
+        .color(Color::RED)
+        .thumbnail(ctx.author().face())
+        .timestamp(Timestamp::now());
+
+    ctx.send(CreateReply::default().ephemeral(true).embed(reply_embed)).await?;
+    Ok(false)
+}
+
+/// Global command check, replies with a denial embed when the caller lacks the command's capability
+pub async fn check(ctx: Context<'_>) -> Result<bool, Error> {
+    let Some(capability) = required_capability(&ctx.command().qualified_name) else {
+        return Ok(true);
+    };
+
+    if has_capability(ctx, capability).await {
+        return Ok(true);
+    }
+
+    let reply_embed = CreateEmbed::default()
+        .title("Permission denied")
+        .field("**Caller**", format!("{} ({})\nMissing capability: {}\nAsk an admin to grant it to your user, one of your roles or this channel.", ctx.author().name, ctx.author().id, capability), false)
+        .color(Color::RED)
+        .thumbnail(ctx.author().face())
+        .timestamp(Timestamp::now());
+
+    ctx.send(CreateReply::default().ephemeral(true).embed(reply_embed)).await?;
+    Ok(false)
+}
@@ -0,0 +1,421 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use poise::serenity_prelude::UserId;
+use tokio::sync::{watch, Notify};
+
+use crate::vrc_client::Action;
+
+/// Which lane an action waits in. The priority lane is always drained first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Lane {
+    Priority,
+    Normal,
+}
+
+#[derive(Clone, Debug)]
+pub struct QueuedAction {
+    pub id: u64,
+    pub user_id: UserId,
+    pub user_name: String,
+    pub lane: Lane,
+    pub action: Action,
+}
+
+/// Where an action is in its lifecycle
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ActionStatus {
+    Queued,
+    Running(SystemTime), // Holds when the inputs are released
+    Completed,
+    Cancelled,
+}
+
+/// Returned when an action is queued
+pub struct Ticket {
+    pub id: u64,
+    pub position: usize, // 1-based position in the serving order
+    pub status: watch::Receiver<ActionStatus>,
+}
+
+#[derive(Debug)]
+pub enum QueueError {
+    UserLimitReached(usize), // Holds the configured per-user limit
+}
+
+impl fmt::Display for QueueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QueueError::UserLimitReached(limit) => write!(f, "You already have {} action(s) waiting", limit),
+        }
+    }
+}
+
+impl std::error::Error for QueueError {}
+
+/// A single lane which round-robins between the users waiting in it
+#[derive(Default)]
+struct FairLane {
+    rotation: VecDeque<UserId>, // Users with pending actions, in serving order
+    pending: HashMap<UserId, VecDeque<QueuedAction>>,
+}
+
+impl FairLane {
+    fn push(&mut self, queued: QueuedAction) {
+        let user_queue = self.pending.entry(queued.user_id).or_default();
+        if user_queue.is_empty() {
+            self.rotation.push_back(queued.user_id);
+        }
+        user_queue.push_back(queued);
+    }
+
+    fn pop(&mut self) -> Option<QueuedAction> {
+        let user_id = self.rotation.pop_front()?;
+        let user_queue = self.pending.get_mut(&user_id)?;
+        let queued = user_queue.pop_front();
+
+        // Users with more waiting go to the back of the rotation
+        if user_queue.is_empty() {
+            self.pending.remove(&user_id);
+        } else {
+            self.rotation.push_back(user_id);
+        }
+        queued
+    }
+
+    /// Removes every pending action matching `predicate`, returning the IDs of those removed
+    fn remove_where(&mut self, predicate: impl Fn(&QueuedAction) -> bool) -> Vec<u64> {
+        let mut removed = Vec::new();
+        for user_queue in self.pending.values_mut() {
+            user_queue.retain(|queued| {
+                let matches = predicate(queued);
+                if matches {
+                    removed.push(queued.id);
+                }
+                !matches
+            });
+        }
+        self.pending.retain(|_, user_queue| !user_queue.is_empty());
+        let pending = &self.pending;
+        self.rotation.retain(|user_id| pending.contains_key(user_id));
+        removed
+    }
+
+    fn user_pending(&self, user_id: UserId) -> usize {
+        self.pending.get(&user_id).map_or(0, VecDeque::len)
+    }
+
+    /// The order in which actions in this lane will be served
+    fn ordered(&self) -> Vec<QueuedAction> {
+        let mut rotation = self.rotation.clone();
+        let mut pending = self.pending.clone();
+        let mut output = Vec::new();
+
+        while let Some(user_id) = rotation.pop_front() {
+            if let Some(user_queue) = pending.get_mut(&user_id) {
+                if let Some(queued) = user_queue.pop_front() {
+                    output.push(queued);
+                }
+                if !user_queue.is_empty() {
+                    rotation.push_back(user_id);
+                }
+            }
+        }
+        output
+    }
+}
+
+#[derive(Default)]
+struct State {
+    next_id: u64,
+    priority: FairLane,
+    normal: FairLane,
+    running: Option<QueuedAction>,
+    stop_running: Option<Arc<Notify>>, // Wakes the movement handler to release the running action early
+    running_cancelled: bool,
+    executed: (Option<chrono::NaiveDate>, u64), // Actions started on the given local day
+    statuses: HashMap<u64, watch::Sender<ActionStatus>>, // Dropped once an action completes or is cancelled
+}
+
+impl State {
+    fn set_status(&mut self, id: u64, status: ActionStatus) {
+        let is_final = matches!(status, ActionStatus::Completed | ActionStatus::Cancelled);
+        if let Some(sender) = self.statuses.get(&id) {
+            sender.send_replace(status);
+        }
+        if is_final {
+            self.statuses.remove(&id);
+        }
+    }
+
+    fn lane_mut(&mut self, lane: Lane) -> &mut FairLane {
+        match lane {
+            Lane::Priority => &mut self.priority,
+            Lane::Normal => &mut self.normal,
+        }
+    }
+}
+
+/// Snapshot of the queue for display purposes
+pub struct QueueSnapshot {
+    pub running: Option<QueuedAction>,
+    pub waiting: Vec<QueuedAction>, // In serving order
+}
+
+/// Per-user fair action scheduler shared between commands and the movement handler
+pub struct Scheduler {
+    state: Mutex<State>,
+    notify: Notify,
+    running: watch::Sender<Option<QueuedAction>>, // Lets displays follow what is being held
+}
+
+impl Default for Scheduler {
+    fn default() -> Self {
+        Scheduler {
+            state: Mutex::default(),
+            notify: Notify::new(),
+            running: watch::Sender::new(None),
+        }
+    }
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Follows the running action, changing whenever one starts or finishes
+    pub fn watch_running(&self) -> watch::Receiver<Option<QueuedAction>> {
+        self.running.subscribe()
+    }
+
+    /// Queues an action, returning its ID and 1-based position in the serving order
+    pub fn push(
+        &self,
+        user_id: UserId,
+        user_name: String,
+        lane: Lane,
+        action: Action,
+        max_pending_per_user: usize,
+    ) -> Result<Ticket, QueueError> {
+        let ticket = {
+            let mut state = self.state.lock().unwrap();
+
+            let pending = state.priority.user_pending(user_id) + state.normal.user_pending(user_id);
+            if pending >= max_pending_per_user {
+                return Err(QueueError::UserLimitReached(max_pending_per_user));
+            }
+
+            state.next_id += 1;
+            let id = state.next_id;
+            state.lane_mut(lane).push(QueuedAction { id, user_id, user_name, lane, action });
+            let (sender, status) = watch::channel(ActionStatus::Queued);
+            state.statuses.insert(id, sender);
+
+            let position = Self::position_in(&state, id).unwrap_or(1);
+            Ticket { id, position, status }
+        };
+
+        self.notify.notify_one();
+        Ok(ticket)
+    }
+
+    fn position_in(state: &State, id: u64) -> Option<usize> {
+        state.priority.ordered().iter()
+            .chain(state.normal.ordered().iter())
+            .position(|queued| queued.id == id)
+            .map(|position| position + 1)
+    }
+
+    /// 1-based position of a waiting action, None once it has started or was cancelled
+    pub fn position(&self, id: u64) -> Option<usize> {
+        Self::position_in(&self.state.lock().unwrap(), id)
+    }
+
+    /// Waits for the next action and marks it as running
+    /// The returned Notify is woken if the action is stopped before its duration is up
+    pub async fn next(&self) -> (QueuedAction, Arc<Notify>) {
+        loop {
+            {
+                let mut state = self.state.lock().unwrap();
+                let queued = state.priority.pop().or_else(|| state.normal.pop());
+                if let Some(queued) = queued {
+                    let stop = Arc::new(Notify::new());
+                    // Durations are capped before queueing, this only guards against a panic holding the lock
+                    let now = SystemTime::now();
+                    let release = now.checked_add(Duration::from_secs(queued.action.duration)).unwrap_or(now);
+                    state.set_status(queued.id, ActionStatus::Running(release));
+                    state.running = Some(queued.clone());
+                    self.running.send_replace(Some(queued.clone()));
+                    state.stop_running = Some(Arc::clone(&stop));
+                    state.running_cancelled = false;
+                    let today = chrono::Local::now().date_naive();
+                    state.executed = match state.executed {
+                        (Some(day), count) if day == today => (Some(day), count + 1),
+                        _ => (Some(today), 1),
+                    };
+                    return (queued, stop);
+                }
+            }
+            self.notify.notified().await;
+        }
+    }
+
+    /// Marks the running action as finished
+    pub fn finish(&self) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(running) = state.running.take() {
+            let status = if state.running_cancelled { ActionStatus::Cancelled } else { ActionStatus::Completed };
+            state.set_status(running.id, status);
+        }
+        state.stop_running = None;
+        self.running.send_replace(None);
+    }
+
+    /// Stops the running action, returns false if nothing matching `predicate` is running
+    fn stop_running_where(state: &mut State, predicate: impl Fn(&QueuedAction) -> bool) -> bool {
+        match (&state.running, &state.stop_running) {
+            (Some(running), Some(stop)) if predicate(running) && !state.running_cancelled => {
+                stop.notify_one();
+                state.running_cancelled = true;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Removes pending actions and stops the running action matching `predicate`, returns how many were cancelled
+    fn cancel_where(&self, predicate: impl Fn(&QueuedAction) -> bool) -> usize {
+        let mut state = self.state.lock().unwrap();
+        let mut removed = state.priority.remove_where(&predicate);
+        removed.extend(state.normal.remove_where(&predicate));
+        for id in &removed {
+            state.set_status(*id, ActionStatus::Cancelled);
+        }
+
+        let mut cancelled = removed.len();
+        if Self::stop_running_where(&mut state, predicate) {
+            cancelled += 1;
+        }
+        cancelled
+    }
+
+    /// Cancels a single action whether it is waiting or running, returns false if it already finished
+    pub fn cancel(&self, id: u64) -> bool {
+        self.cancel_where(|queued| queued.id == id) > 0
+    }
+
+    /// Cancels a user's pending actions and stops their running one, returns how many were cancelled
+    /// With `user_id` as None every action is cancelled
+    pub fn cancel_user(&self, user_id: Option<UserId>) -> usize {
+        self.cancel_where(|queued| user_id.is_none_or(|user_id| queued.user_id == user_id))
+    }
+
+    /// Number of actions started today, local time
+    pub fn executed_today(&self) -> u64 {
+        match self.state.lock().unwrap().executed {
+            (Some(day), count) if day == chrono::Local::now().date_naive() => count,
+            _ => 0,
+        }
+    }
+
+    pub fn snapshot(&self) -> QueueSnapshot {
+        let state = self.state.lock().unwrap();
+        let mut waiting = state.priority.ordered();
+        waiting.extend(state.normal.ordered());
+        QueueSnapshot {
+            running: state.running.clone(),
+            waiting,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn action() -> Action {
+        Action { duration: 1, movement: Some("Forward".to_owned()), look: None, run: None, jump: None }
+    }
+
+    fn queued(id: u64, user: u64) -> QueuedAction {
+        QueuedAction { id, user_id: UserId::new(user), user_name: user.to_string(), lane: Lane::Normal, action: action() }
+    }
+
+    fn drain(lane: &mut FairLane) -> Vec<u64> {
+        std::iter::from_fn(|| lane.pop()).map(|queued| queued.id).collect()
+    }
+
+    #[test]
+    fn pop_takes_turns_between_users() {
+        let mut lane = FairLane::default();
+        lane.push(queued(1, 10));
+        lane.push(queued(2, 10));
+        lane.push(queued(3, 10));
+        lane.push(queued(4, 20));
+        lane.push(queued(5, 30));
+        lane.push(queued(6, 20));
+
+        let expected = vec![1, 4, 5, 2, 6, 3];
+        assert_eq!(lane.ordered().iter().map(|queued| queued.id).collect::<Vec<_>>(), expected);
+        assert_eq!(drain(&mut lane), expected);
+        assert!(lane.pending.is_empty());
+    }
+
+    #[test]
+    fn remove_where_drops_users_left_without_actions() {
+        let mut lane = FairLane::default();
+        lane.push(queued(1, 10));
+        lane.push(queued(2, 20));
+        lane.push(queued(3, 10));
+        lane.push(queued(4, 30));
+
+        let mut removed = lane.remove_where(|queued| queued.user_id == UserId::new(10) || queued.id == 4);
+        removed.sort();
+        assert_eq!(removed, vec![1, 3, 4]);
+        assert_eq!(lane.user_pending(UserId::new(10)), 0);
+        assert_eq!(lane.rotation, VecDeque::from([UserId::new(20)]));
+        assert_eq!(drain(&mut lane), vec![2]);
+    }
+
+    #[test]
+    fn priority_lane_is_served_first() {
+        let scheduler = Scheduler::new();
+        let normal = scheduler.push(UserId::new(10), "10".to_owned(), Lane::Normal, action(), 3).unwrap();
+        let priority = scheduler.push(UserId::new(20), "20".to_owned(), Lane::Priority, action(), 3).unwrap();
+
+        assert_eq!(priority.position, 1);
+        assert_eq!(scheduler.position(normal.id), Some(2));
+    }
+
+    #[test]
+    fn push_enforces_the_per_user_limit_across_lanes() {
+        let scheduler = Scheduler::new();
+        let user = UserId::new(10);
+        scheduler.push(user, "10".to_owned(), Lane::Normal, action(), 2).unwrap();
+        scheduler.push(user, "10".to_owned(), Lane::Priority, action(), 2).unwrap();
+
+        let result = scheduler.push(user, "10".to_owned(), Lane::Normal, action(), 2);
+        assert!(matches!(result, Err(QueueError::UserLimitReached(2))));
+        assert!(scheduler.push(UserId::new(20), "20".to_owned(), Lane::Normal, action(), 2).is_ok());
+
+        // Cancelling frees up room again
+        assert_eq!(scheduler.cancel_user(Some(user)), 2);
+        assert!(scheduler.push(user, "10".to_owned(), Lane::Normal, action(), 2).is_ok());
+    }
+
+    #[tokio::test]
+    async fn huge_durations_do_not_poison_the_queue() {
+        let scheduler = Scheduler::new();
+        let huge = Action { duration: u64::MAX, ..action() };
+        let ticket = scheduler.push(UserId::new(10), "10".to_owned(), Lane::Normal, huge, 3).unwrap();
+
+        let (queued, _stop) = scheduler.next().await;
+        assert_eq!(queued.id, ticket.id);
+        scheduler.finish();
+        assert_eq!(*ticket.status.borrow(), ActionStatus::Completed);
+        assert!(scheduler.snapshot().running.is_none());
+    }
+}
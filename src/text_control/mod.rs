@@ -0,0 +1,293 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use log::error;
+use poise::serenity_prelude::{self as serenity, ReactionType, UserId};
+
+use crate::dispatch::{self, Submitter};
+use crate::vrc_client::Action;
+use crate::Data;
+
+/// Inputs a keyword can stand for
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextInput {
+    Forward,
+    Backward,
+    Left,
+    Right,
+    LookLeft,
+    LookRight,
+    Run,
+    Jump,
+}
+
+impl TextInput {
+    /// Adds this input to an action
+    pub fn apply(self, action: &mut Action) {
+        match self {
+            TextInput::Forward => action.movement = Some("Forward".to_owned()),
+            TextInput::Backward => action.movement = Some("Backward".to_owned()),
+            TextInput::Left => action.movement = Some("Left".to_owned()),
+            TextInput::Right => action.movement = Some("Right".to_owned()),
+            TextInput::LookLeft => action.look = Some("Left".to_owned()),
+            TextInput::LookRight => action.look = Some("Right".to_owned()),
+            TextInput::Run => action.run = Some(true),
+            TextInput::Jump => action.jump = Some(true),
+        }
+    }
+}
+
+/// Keywords used when `[text_control.keywords]` is empty
+pub fn default_keywords() -> HashMap<String, TextInput> {
+    [
+        ("w", TextInput::Forward), ("forward", TextInput::Forward), ("up", TextInput::Forward),
+        ("s", TextInput::Backward), ("backward", TextInput::Backward), ("back", TextInput::Backward),
+        ("a", TextInput::Left), ("left", TextInput::Left),
+        ("d", TextInput::Right), ("right", TextInput::Right),
+        ("q", TextInput::LookLeft), ("e", TextInput::LookRight),
+        ("run", TextInput::Run), ("sprint", TextInput::Run),
+        ("jump", TextInput::Jump), ("space", TextInput::Jump),
+    ]
+        .into_iter()
+        .map(|(keyword, input)| (keyword.to_owned(), input))
+        .collect()
+}
+
+/// Number of single character edits or swaps of neighbouring characters needed to turn `a` into `b`
+/// This is the optimal string alignment distance, so "jmup" is one typo away from "jump"
+fn typo_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    distances[0] = (0..=b.len()).collect();
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1)
+                .min(distances[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
+        }
+    }
+    distances[a.len()][b.len()]
+}
+
+/// Typos tolerated in a keyword, none in very short ones since any letter would be one typo away
+fn allowed_typos(keyword: &str) -> usize {
+    match keyword.chars().count() {
+        0..=2 => 0,
+        3..=7 => 1,
+        _ => 2,
+    }
+}
+
+/// Finds the keyword a word stands for, allowing a typo or two depending on the keyword's length
+fn lookup(word: &str, keywords: &HashMap<String, TextInput>) -> Option<TextInput> {
+    if let Some(input) = keywords.get(word) {
+        return Some(*input);
+    }
+
+    let mut closest: Option<(usize, &String)> = None;
+    for keyword in keywords.keys() {
+        let distance = typo_distance(word, keyword);
+        // Comparing keywords on ties keeps the match deterministic regardless of map order
+        if distance <= allowed_typos(keyword) && closest.is_none_or(|(best, best_keyword)| (distance, keyword) < (best, best_keyword)) {
+            closest = Some((distance, keyword));
+        }
+    }
+    closest.map(|(_, keyword)| keywords[keyword])
+}
+
+/// Turns a bare message such as "a 3" or "run left" into an action
+/// Returns None for anything that isn't made up entirely of keywords and an optional duration
+pub fn parse(message: &str, keywords: &HashMap<String, TextInput>, default_duration: u64, max_duration: u64) -> Option<Action> {
+    let mut action = Action {
+        duration: default_duration,
+        movement: None,
+        look: None,
+        run: None,
+        jump: None,
+    };
+    let mut has_input = false;
+    let mut has_duration = false;
+
+    for word in message.to_lowercase().split_whitespace() {
+        if let Ok(duration) = word.parse::<u64>() {
+            if has_duration {
+                return None;
+            }
+            action.duration = duration.min(max_duration);
+            has_duration = true;
+            continue;
+        }
+
+        lookup(word, keywords)?.apply(&mut action);
+        has_input = true;
+    }
+
+    has_input.then_some(action)
+}
+
+/// Tracks when each user last had a message turned into an action
+#[derive(Default)]
+pub struct Throttle {
+    last: Mutex<HashMap<UserId, Instant>>,
+}
+
+impl Throttle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the attempt and returns true if the user is allowed to act now
+    pub fn allow(&self, user_id: UserId, interval: Duration) -> bool {
+        let mut last = self.last.lock().unwrap();
+        let now = Instant::now();
+        // Users whose interval has passed are treated like new ones, so they needn't be kept
+        last.retain(|_, previous| now.duration_since(*previous) < interval);
+        match last.get(&user_id) {
+            Some(previous) if now.duration_since(*previous) < interval => false,
+            _ => {
+                last.insert(user_id, now);
+                true
+            }
+        }
+    }
+}
+
+async fn react(ctx: &serenity::Context, message: &serenity::Message, emoji: &str) {
+    if let Err(e) = message.react(ctx, ReactionType::Unicode(emoji.to_owned())).await {
+        error!("Failed to react to text control message: {}", e);
+    }
+}
+
+/// Turns messages in the text control channel into actions
+pub async fn handle_message(ctx: &serenity::Context, data: &Data, message: &serenity::Message) {
+    if message.author.bot {
+        return;
+    }
+
+    let (action, throttle, is_owner) = {
+        let config = data.config.lock().unwrap();
+        let settings = &config.text_control;
+        if settings.channel.parse::<u64>() != Ok(message.channel_id.get()) {
+            return;
+        }
+
+        let keywords = if settings.keywords.is_empty() { default_keywords() } else { settings.keywords.clone() };
+        let Some(action) = parse(&message.content, &keywords, settings.duration, settings.max_duration) else {
+            return;
+        };
+        (action, Duration::from_secs(settings.throttle_secs), config.is_owner(message.author.id.get()))
+    };
+
+    if !is_owner && !data.text_throttle.allow(message.author.id, throttle) {
+        react(ctx, message, "⏳").await;
+        return;
+    }
+
+    let submitter = Submitter {
+        user_id: message.author.id,
+        user_name: message.author.name.clone(),
+        role_ids: message.member.as_ref()
+            .map(|member| member.roles.iter().map(|role_id| role_id.to_string()).collect())
+            .unwrap_or_default(),
+        channel_id: message.channel_id,
+        guild_id: message.guild_id,
+        source: "text",
+    };
+    if let Some(emoji) = dispatch::submit(data, &submitter, action).emoji() {
+        react(ctx, message, emoji).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_default(message: &str) -> Option<Action> {
+        parse(message, &default_keywords(), 1, 10)
+    }
+
+    #[test]
+    fn aliases_map_to_the_same_input() {
+        for message in ["w", "forward", "up", "W", "FORWARD"] {
+            assert_eq!(parse_default(message).unwrap().movement.as_deref(), Some("Forward"), "{}", message);
+        }
+        assert_eq!(parse_default("space").unwrap().jump, Some(true));
+        assert_eq!(parse_default("sprint").unwrap().run, Some(true));
+        assert_eq!(parse_default("e").unwrap().look.as_deref(), Some("Right"));
+    }
+
+    #[test]
+    fn inputs_combine() {
+        let action = parse_default("run left jump").unwrap();
+        assert_eq!(action.movement.as_deref(), Some("Left"));
+        assert_eq!(action.run, Some(true));
+        assert_eq!(action.jump, Some(true));
+    }
+
+    #[test]
+    fn durations_default_clamp_and_appear_once() {
+        assert_eq!(parse_default("a").unwrap().duration, 1);
+        assert_eq!(parse_default("a 3").unwrap().duration, 3);
+        assert_eq!(parse_default("3 a").unwrap().duration, 3);
+        assert_eq!(parse_default("a 999999999999").unwrap().duration, 10);
+        assert!(parse_default("a 3 4").is_none());
+        assert!(parse_default("3").is_none());
+    }
+
+    #[test]
+    fn other_messages_are_ignored() {
+        assert!(parse_default("").is_none());
+        assert!(parse_default("hello there").is_none());
+        assert!(parse_default("w please").is_none());
+    }
+
+    #[test]
+    fn typos_are_tolerated() {
+        assert_eq!(parse_default("jmup").unwrap().jump, Some(true));
+        assert_eq!(parse_default("forwrad").unwrap().movement.as_deref(), Some("Forward"));
+        assert_eq!(parse_default("rnu").unwrap().run, Some(true));
+        assert_eq!(parse_default("lef").unwrap().movement.as_deref(), Some("Left"));
+        assert_eq!(parse_default("bakcwrd").unwrap().movement.as_deref(), Some("Backward"));
+    }
+
+    #[test]
+    fn short_keywords_need_an_exact_match() {
+        assert!(parse_default("x").is_none());
+        assert!(parse_default("z 3").is_none());
+        assert!(parse_default("jumping").is_none());
+    }
+
+    #[test]
+    fn typo_distance_counts_swaps_as_one_edit() {
+        assert_eq!(typo_distance("jump", "jump"), 0);
+        assert_eq!(typo_distance("jmup", "jump"), 1);
+        assert_eq!(typo_distance("jum", "jump"), 1);
+        assert_eq!(typo_distance("jumpp", "jump"), 1);
+        assert_eq!(typo_distance("jamp", "jump"), 1);
+        assert_eq!(typo_distance("", "run"), 3);
+    }
+
+    #[test]
+    fn throttle_allows_again_after_the_interval() {
+        let throttle = Throttle::new();
+        let user = UserId::new(1);
+        assert!(throttle.allow(user, Duration::from_secs(60)));
+        assert!(!throttle.allow(user, Duration::from_secs(60)));
+        assert!(throttle.allow(user, Duration::ZERO));
+        assert!(throttle.allow(UserId::new(2), Duration::ZERO));
+        assert!(throttle.last.lock().unwrap().len() <= 1);
+    }
+}
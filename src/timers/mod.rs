@@ -0,0 +1,239 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+use log::error;
+use poise::serenity_prelude::{self as serenity, ChannelId, CreateAllowedMentions, CreateMessage, UserId};
+
+use crate::chatbox;
+use crate::chatbox::manager::{ChatboxManager, ChatboxMessage, Priority};
+use crate::config::Config;
+
+// Countdowns are queued under this key so each refresh replaces the last one still waiting
+const KEY: &str = "timers";
+
+// How long the "time's up" line stays in the chatbox
+const FINISHED_DISPLAY: Duration = Duration::from_secs(5);
+
+/// A countdown shown in the chatbox until it ends
+#[derive(Clone)]
+pub struct Timer {
+    pub id: u64,
+    pub label: String,
+    pub ends: SystemTime,
+    pub user: UserId,       // Pinged once the timer ends
+    pub channel: ChannelId, // Where the ping is sent
+}
+
+#[derive(Default)]
+struct State {
+    next_id: u64,
+    timers: Vec<Timer>, // Ordered by when they end
+}
+
+/// Running timers and countdowns, any number of them can run at once
+#[derive(Default)]
+pub struct Timers {
+    state: Mutex<State>,
+}
+
+impl Timers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts a timer, returns it or None if `max_timers` are already running
+    pub fn start(&self, label: String, ends: SystemTime, user: UserId, channel: ChannelId, max_timers: usize) -> Option<Timer> {
+        let mut state = self.state.lock().unwrap();
+        if state.timers.len() >= max_timers {
+            return None;
+        }
+        state.next_id += 1;
+        let timer = Timer { id: state.next_id, label, ends, user, channel };
+        let index = state.timers.partition_point(|running| running.ends <= ends);
+        state.timers.insert(index, timer.clone());
+        Some(timer)
+    }
+
+    /// Stops the running timers `matches` picks, returns them
+    pub fn cancel_where(&self, matches: impl Fn(&Timer) -> bool) -> Vec<Timer> {
+        let mut state = self.state.lock().unwrap();
+        let (cancelled, running) = std::mem::take(&mut state.timers).into_iter().partition(|timer| matches(timer));
+        state.timers = running;
+        cancelled
+    }
+
+    /// Removes and returns the timers which have ended
+    fn take_finished(&self) -> Vec<Timer> {
+        let now = SystemTime::now();
+        let mut state = self.state.lock().unwrap();
+        let finished = state.timers.partition_point(|timer| timer.ends <= now);
+        state.timers.drain(..finished).collect()
+    }
+
+    fn running(&self) -> Vec<Timer> {
+        self.state.lock().unwrap().timers.clone()
+    }
+}
+
+/// Parses durations like "90", "45s", "5m" or "1h30m", bare numbers are seconds
+pub fn parse_duration(text: &str) -> Option<Duration> {
+    let mut seconds: u64 = 0;
+    let mut number = String::new();
+    for c in text.trim().to_lowercase().chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let unit = match c {
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            ' ' if number.is_empty() => continue,
+            _ => return None,
+        };
+        seconds = seconds.checked_add(number.parse::<u64>().ok()?.checked_mul(unit)?)?;
+        number.clear();
+    }
+    if !number.is_empty() {
+        seconds = seconds.checked_add(number.parse().ok()?)?;
+    }
+    (seconds > 0).then(|| Duration::from_secs(seconds))
+}
+
+/// Parses a point in time as a Unix timestamp, a Discord timestamp such as <t:1700000000:R>,
+/// an RFC 3339 date, a local "YYYY-MM-DD HH:MM" or a local "HH:MM", which means the next time the clock shows it
+pub fn parse_timestamp(text: &str) -> Option<SystemTime> {
+    let text = text.trim();
+    let unix = text.strip_prefix("<t:")
+        .and_then(|rest| rest.strip_suffix('>'))
+        .map_or(text, |inner| inner.split(':').next().unwrap_or_default());
+    if let Ok(seconds) = unix.parse::<u64>() {
+        return UNIX_EPOCH.checked_add(Duration::from_secs(seconds));
+    }
+    if let Ok(date) = DateTime::parse_from_rfc3339(text) {
+        return Some(date.into());
+    }
+    for format in ["%Y-%m-%d %H:%M", "%Y-%m-%d %H:%M:%S"] {
+        if let Ok(date) = NaiveDateTime::parse_from_str(text, format) {
+            return Local.from_local_datetime(&date).earliest().map(SystemTime::from);
+        }
+    }
+
+    let time = NaiveTime::parse_from_str(text, "%H:%M").ok()?;
+    let now = Local::now();
+    let today: NaiveDate = now.date_naive();
+    let next = [today, today.succ_opt()?].into_iter()
+        .filter_map(|day| Local.from_local_datetime(&day.and_time(time)).earliest())
+        .find(|date| *date > now)?;
+    Some(next.into())
+}
+
+/// Seconds since the Unix epoch, for Discord timestamps
+pub fn unix_seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs())
+}
+
+/// Time left as m:ss, or h:mm:ss from an hour up
+fn format_remaining(remaining: Duration) -> String {
+    let seconds = remaining.as_secs();
+    let (hours, minutes, seconds) = (seconds / 3600, seconds % 3600 / 60, seconds % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
+
+/// Pings the owner of a finished timer in the channel it was started from
+async fn announce(http: &serenity::Http, timer: &Timer) {
+    let message = CreateMessage::new()
+        .content(format!("<@{}> ⏰ **{}** is up!", timer.user, timer.label))
+        .allowed_mentions(CreateAllowedMentions::new().users([timer.user]));
+    if let Err(e) = timer.channel.send_message(http, message).await {
+        error!("Failed to announce finished timer {}: {}", timer.id, e);
+    }
+}
+
+/// Shows every running timer in the chatbox, refreshed every `timers.refresh_secs`, and announces finished ones
+pub async fn run(http: Arc<serenity::Http>, timers: Arc<Timers>, manager: Arc<ChatboxManager>, config: Arc<Mutex<Config>>) {
+    loop {
+        let refresh = Duration::from_secs(config.lock().unwrap().timers.refresh_secs.max(1));
+
+        let finished = timers.take_finished();
+        if !finished.is_empty() {
+            let text = finished.iter()
+                .map(|timer| format!("⏰ {} is up!", timer.label))
+                .collect::<Vec<String>>()
+                .join("\n");
+            if let Some(page) = chatbox::paginate(&text).into_iter().next() {
+                // Unkeyed so the next countdown doesn't replace it
+                manager.submit(ChatboxMessage::new(page, Priority::Normal, FINISHED_DISPLAY));
+            }
+            for timer in &finished {
+                announce(&http, timer).await;
+            }
+        }
+
+        // Only the timers ending soonest are shown when they don't all fit
+        let now = SystemTime::now();
+        let running = timers.running();
+        if !running.is_empty() {
+            let text = running.iter()
+                .map(|timer| format!("⏳ {} {}", timer.label, format_remaining(timer.ends.duration_since(now).unwrap_or_default())))
+                .collect::<Vec<String>>()
+                .join("\n");
+            if let Some(page) = chatbox::paginate(&text).into_iter().next() {
+                manager.submit(ChatboxMessage::new(page, Priority::Normal, Duration::ZERO).key(KEY));
+            }
+        } else {
+            // The last timers may have been cancelled while their countdown was still waiting
+            manager.withdraw(KEY);
+        }
+
+        // Wake up early for a timer ending before the next refresh
+        let wait = running.first()
+            .and_then(|timer| timer.ends.duration_since(now).ok())
+            .map_or(refresh, |remaining| remaining.min(refresh));
+        tokio::time::sleep(wait).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn start(timers: &Timers, label: &str, user: u64, ends_in: u64) -> Timer {
+        let ends = SystemTime::now() + Duration::from_secs(ends_in);
+        timers.start(label.to_owned(), ends, UserId::new(user), ChannelId::new(1), 10).unwrap()
+    }
+
+    #[test]
+    fn durations_are_parsed() {
+        assert_eq!(parse_duration("90"), Some(Duration::from_secs(90)));
+        assert_eq!(parse_duration("1h 30m"), Some(Duration::from_secs(5400)));
+        assert_eq!(parse_duration("0s"), None);
+        assert_eq!(parse_duration(&format!("{}h", u64::MAX)), None);
+    }
+
+    #[test]
+    fn huge_timestamps_are_rejected() {
+        assert_eq!(parse_timestamp("1700000000"), Some(UNIX_EPOCH + Duration::from_secs(1_700_000_000)));
+        assert_eq!(parse_timestamp(&u64::MAX.to_string()), None);
+        assert_eq!(parse_timestamp(&format!("<t:{}:R>", u64::MAX)), None);
+    }
+
+    #[test]
+    fn cancelled_timers_stop_running() {
+        let timers = Timers::new();
+        let first = start(&timers, "first", 1, 60);
+        start(&timers, "second", 2, 120);
+        start(&timers, "third", 1, 180);
+
+        let cancelled = timers.cancel_where(|timer| timer.id == first.id);
+        assert_eq!(cancelled.len(), 1);
+        assert_eq!(timers.cancel_where(|timer| timer.user == UserId::new(1)).len(), 1);
+        let running: Vec<String> = timers.running().into_iter().map(|timer| timer.label).collect();
+        assert_eq!(running, ["second"]);
+    }
+}
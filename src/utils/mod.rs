@@ -1,8 +1,12 @@
-// TODO: Add functionality with VRChat API endpoints (these imports will be useful for that)
-// For uploading text files
-use std::fs::File;
-use std::io::Write;
-use reqwest;
+// TODO: Add functionality with VRChat API endpoints
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use log::{error, warn};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 
 // Thanks https://stackoverflow.com/questions/38461429/how-can-i-truncate-a-string-to-have-at-most-n-characters
 pub fn truncate(s: &str, max_chars: usize) -> &str {
@@ -30,4 +34,62 @@ pub fn format_list(name: &str, items: &[String]) -> String {
         }
     }
     output
-}
\ No newline at end of file
+}
+/// Formats seconds as e.g. "1h 2m 3s", leaving out leading zero units
+pub fn format_duration(seconds: u64) -> String {
+    let (hours, minutes, seconds) = (seconds / 3600, seconds % 3600 / 60, seconds % 60);
+    if hours > 0 {
+        format!("{}h {}m {}s", hours, minutes, seconds)
+    } else if minutes > 0 {
+        format!("{}m {}s", minutes, seconds)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+/// Writes a file through a temporary file and a rename, so a crash never leaves it half written
+pub fn write_atomic(path: &Path, contents: &str) -> io::Result<()> {
+    let mut temp_name = path.file_name().unwrap_or_default().to_owned();
+    temp_name.push(".tmp");
+    let temp_path = path.with_file_name(temp_name);
+    fs::write(&temp_path, contents)?;
+    fs::rename(&temp_path, path)
+}
+
+/// Reads state saved with `save_state`, starting from the default when the file is missing or can't be parsed
+/// `name` describes the state in log messages, e.g. "access lists"
+pub fn load_state<T: DeserializeOwned + Default>(path: &Path, name: &str) -> T {
+    match fs::read_to_string(path) {
+        Ok(toml_string) => toml::from_str(&toml_string).unwrap_or_else(|e| {
+            warn!("Failed to parse {}, starting with empty {}: {}", path.display(), name, e);
+            T::default()
+        }),
+        Err(_) => T::default(),
+    }
+}
+
+/// Saves state as TOML with `write_atomic`, logging failures, returns whether it was written
+pub fn save_state<T: Serialize>(path: &Path, name: &str, state: &T) -> bool {
+    let toml_string = match toml::to_string(state) {
+        Ok(toml_string) => toml_string,
+        Err(e) => {
+            error!("Failed to serialize {}: {}", name, e);
+            return false;
+        }
+    };
+    match write_atomic(path, &toml_string) {
+        Ok(()) => true,
+        Err(e) => {
+            error!("Failed to save {} to {}: {}", name, path.display(), e);
+            false
+        }
+    }
+}
+
+/// Directory to watch for changes to a file, editors often replace files instead of writing to them
+pub fn watch_dir(path: &Path) -> PathBuf {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_owned(),
+        _ => PathBuf::from("."),
+    }
+}
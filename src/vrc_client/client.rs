@@ -6,12 +6,11 @@ use rosc::encoder;
 // rosc types
 use rosc::{OscMessage, OscPacket, OscType};
 // Import from traits.rs
-use crate::vrc_client::traits::{Data, Input, Avatar};
+use crate::vrc_client::traits::{Data, Input};
 
-use log::{debug, info, warn, error};
+use log::{debug, error};
 use std::net::{Ipv4Addr, SocketAddrV4, UdpSocket};
 use std::{thread, time};
-use tokio::time::sleep as t_sleep; // TODO: Fix this awful practice
 
 fn sleep (ms: u64) {
     thread::sleep(time::Duration::from_millis(ms));
@@ -29,8 +28,11 @@ fn capitalize(s: &str) -> String {
 
 #[derive(Debug)]
 pub struct Client {
+    #[allow(dead_code)] // Kept for debugging, only the string forms are used to send
     pub receive_addr: SocketAddrV4,
+    #[allow(dead_code)]
     pub receive_addr_str: String,
+    #[allow(dead_code)]
     pub transmit_addr: SocketAddrV4,
     pub transmit_addr_str: String,
     pub sock: UdpSocket,
@@ -46,7 +48,7 @@ impl Data for Client {
     fn send_data(&self, param_name: &str, param_arg: Vec<OscType>) {
         // Create OSC/1.0 Message buffer with parameter name and parameter value/arg
         let msg_buf = encoder::encode(&OscPacket::Message(OscMessage {
-            addr: param_name.to_string(),
+            addr: param_name.to_owned(),
             args: param_arg,
         }))
             .unwrap();
@@ -88,7 +90,7 @@ impl Data for Client {
             If the decoded packet "pkt" is of OSC type Message,
             the OSC address and OSC args are printed to the CLI.
         */
-        if buffer_len <= 0 { None }
+        if buffer_len == 0 { None }
         else {
             let pkt = match rosc::decoder::decode_udp(&buf) {
                 Ok(pkt) => pkt,
@@ -116,7 +118,7 @@ impl Input for Client {
     // Forward and backward movement, more precise than input_move
     // vertical takes f32 from -1 to 1
     fn input_vertical(&self, velocity: f32) {
-        let param_name: String = "/input/Vertical".to_string();
+        let param_name: String = "/input/Vertical".to_owned();
         let param_arg: OscType = OscType::Float(velocity);
         self.send_data(&param_name, vec![param_arg])
     }
@@ -124,7 +126,7 @@ impl Input for Client {
     // Left and right movement, more precise than input_move
     // horizontal takes f32 from -1 to 1
     fn input_horizontal(&self, velocity: f32) {
-        let param_name: String = "/input/Horizontal".to_string();
+        let param_name: String = "/input/Horizontal".to_owned();
         let param_arg: OscType = OscType::Float(velocity);
         self.send_data(&param_name, vec![param_arg])
     }
@@ -132,7 +134,7 @@ impl Input for Client {
     // Forward and backward movement for a held object
     // Takes f32 from -1 to 1
     fn input_move_hold(&self, velocity: f32) {
-        let param_name: String = "/input/MoveHoldFB".to_string();
+        let param_name: String = "/input/MoveHoldFB".to_owned();
         let param_arg: OscType = OscType::Float(velocity);
         self.send_data(&param_name, vec![param_arg])
     }
@@ -140,7 +142,7 @@ impl Input for Client {
     // Clockwise and counter-clockwise movement for a held object
     // Takes f32 from -1 to 1
     fn input_spin_hold_cw(&self, velocity: f32) {
-        let param_name: String = "/input/SpinHoldCwCcw".to_string();
+        let param_name: String = "/input/SpinHoldCwCcw".to_owned();
         let param_arg: OscType = OscType::Float(velocity);
         self.send_data(&param_name, vec![param_arg])
     }
@@ -148,7 +150,7 @@ impl Input for Client {
     // Up and down movement for a held object
     // Takes f32 from -1 to 1
     fn input_spin_hold_vertical(&self, velocity: f32) {
-        let param_name: String = "/input/SpinHoldUD".to_string();
+        let param_name: String = "/input/SpinHoldUD".to_owned();
         let param_arg: OscType = OscType::Float(velocity);
         self.send_data(&param_name, vec![param_arg])
     }
@@ -156,7 +158,7 @@ impl Input for Client {
     // Left and right movement for a held object
     // Takes f32 from -1 to 1
     fn input_spin_hold_horizontal(&self, velocity: f32) {
-        let param_name: String = "/input/SpinHoldLR".to_string();
+        let param_name: String = "/input/SpinHoldLR".to_owned();
         let param_arg: OscType = OscType::Float(velocity);
         self.send_data(&param_name, vec![param_arg])
     }
@@ -181,7 +183,7 @@ impl Input for Client {
 
     // Jump takes ints 1 and 0 -> 1 is activated, 0 is reset
     fn input_jump(&self) {
-        let param_name: String = "/input/Jump".to_string();
+        let param_name: String = "/input/Jump".to_owned();
         self.send_data(&param_name, vec![OscType::Int(1)]); // Activate jump
         sleep(10); // Required sleep time for "keypresses" to register
         self.send_data(&param_name, vec![OscType::Int(0)]) // Reset jump
@@ -189,7 +191,7 @@ impl Input for Client {
 
     // Run takes ints 1 and 0 -> 1 is activated, 0 is inactive
     fn input_run(&self, toggle: i32) {
-        let param_name: String = "/input/Run".to_string();
+        let param_name: String = "/input/Run".to_owned();
         let param_arg: OscType = OscType::Int(toggle);
         self.send_data(&param_name, vec![param_arg]) // 1 = running | 0 = walking
     }
@@ -199,17 +201,24 @@ impl Input for Client {
     // b = don't open keyboard (post straight to chatbox)
     // n = don't play notification sound
     fn chatbox_message(&self, message: &str) {
-        let verified_message: &str = &message;
+        let verified_message: &str = message;
 
         debug!("Sent '{}'", &verified_message);
 
         let param_name: &str = "/chatbox/input";
         let param_arg: Vec<OscType> = vec![
-            OscType::String(verified_message.to_string()), // Chatbox text
+            OscType::String(verified_message.to_owned()), // Chatbox text
             OscType::Bool(true), // Don't open keyboard (post straight to chatbox)
             OscType::Bool(false)]; // Don't play notification sound
         self.send_data(param_name, param_arg)
     }
+
+    // Takes a bool -> true shows the typing indicator, false hides it
+    fn chatbox_typing(&self, typing: bool) {
+        let param_name: &str = "/chatbox/typing";
+        let param_arg: OscType = OscType::Bool(typing);
+        self.send_data(param_name, vec![param_arg])
+    }
 }
 
 impl Client {
@@ -221,7 +230,7 @@ impl Client {
         let receive_addr: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(127, 0, 0, 1), receiver_port);
         let transmit_addr: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(127, 0, 0, 1), transmitter_port);
 
-        let socket = match UdpSocket::bind(&receive_addr) {
+        let socket = match UdpSocket::bind(receive_addr) {
             Ok(success) =>  {
                 debug!("Successfully bound to {:?}", &receive_addr);
                 success
@@ -243,11 +252,13 @@ impl Client {
         }
     }
 
+    #[allow(dead_code)] // Unfinished
     pub fn test_socket(&self) -> bool {
         todo!();
     }
 
     // Ensure that you can run and jump before moving. The necessity of this is uncertain.
+    #[allow(dead_code)]
     pub fn input_button_init(&self) {
         self.send_data("/input/Jump", vec![OscType::Int(0)]); // Initialize jump to 0
         sleep(10);
@@ -257,6 +268,7 @@ impl Client {
     }
 
     // This is all super hardcoded. It's just a demonstration, and it's kind of cool in public lobbies.
+    #[allow(dead_code)] // Manual calibration, not bound to a command
     pub fn input_test(&self) {
         // Moving left/right for 1750ms ~= 360 degrees
 
@@ -312,6 +324,7 @@ impl Client {
     }
 
     // Cool spinning effect for picked up items
+    #[allow(dead_code)]
     pub fn input_rotate_axis_left(&self) {
         // Spacing in timing as to not exceed rate limit
         self.input_spin_hold_cw(-0.5);
@@ -322,6 +335,7 @@ impl Client {
     }
 
     // Cool spinning effect for picked up items
+    #[allow(dead_code)]
     pub fn input_rotate_axis_right(&self) {
         // Spacing in timing as to not exceed rate limit
         self.input_spin_hold_cw(0.5);
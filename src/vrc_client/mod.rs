@@ -1,5 +1,3 @@
-use crate::vrc_client::traits::Input;
-
 pub mod traits;
 pub mod client;
 
@@ -5,6 +5,7 @@ use rosc::OscType;
 
 pub trait Data {
     fn send_data(&self, param_name: &str, param_arg: Vec<OscType>);
+    #[allow(dead_code)] // Nothing listens for VRChat's output yet
     fn recv_data(&self) -> Option<(String, Vec<OscType>)>;
 }
 
@@ -12,30 +13,37 @@ pub trait Data {
 pub trait Input {
     /*
      * AXES
+     * Not bound to any command yet, only input_test uses them
      */
 
     /// Forward and backward movement, more precise than input_move
     /// Takes f32 from -1 to 1
+    #[allow(dead_code)]
     fn input_vertical(&self, velocity: f32);
 
     /// Left and right movement, more precise than input_move
     /// Takes f32 from -1 to 1
+    #[allow(dead_code)]
     fn input_horizontal(&self, velocity: f32);
 
     /// Forward and backward movement for a held object
     /// Takes f32 from -1 to 1
+    #[allow(dead_code)]
     fn input_move_hold(&self, velocity: f32);
 
     /// Clockwise and counter-clockwise movement for a held object
     /// Takes f32 from -1 to 1
+    #[allow(dead_code)]
     fn input_spin_hold_cw(&self, velocity: f32);
 
     /// Up and down movement for a held object
     /// Takes f32 from -1 to 1
+    #[allow(dead_code)]
     fn input_spin_hold_vertical(&self, velocity: f32);
 
     /// Left and right movement for a held object
     /// Takes f32 from -1 to 1
+    #[allow(dead_code)]
     fn input_spin_hold_horizontal(&self, velocity: f32);
 
     /*
@@ -59,7 +67,11 @@ pub trait Input {
     /// b = don't open keyboard (post straight to chatbox)
     /// n = don't play notification sound
     fn chatbox_message(&self, message: &str);
+
+    /// Turns the typing indicator above the chatbox on or off
+    fn chatbox_typing(&self, typing: bool);
 }
 
 // Output traits for client (receiving data from surroundings)
+#[allow(dead_code)]
 pub trait Avatar {}
\ No newline at end of file